@@ -0,0 +1,137 @@
+//! Measures how many progress updates per second a job can absorb.
+//!
+//! Run with `cargo bench`. Each case reports the wall time of a fixed number of
+//! updates so the cost of rendering on every call can be compared with updates
+//! coalesced by `min_update_interval` and by the background refresh thread,
+//! and the per-render cost when many jobs share one template body.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clx::progress::{
+    ProgressJobBuilder, ProgressOutput, ProgressStatus, clear_jobs, set_output, stop_clear,
+};
+
+fn report(name: &str, updates: usize, elapsed: Duration) {
+    let per_sec = updates as f64 / elapsed.as_secs_f64();
+    println!(
+        "{name:<40} {updates:>10} updates in {:>9.2?} ({per_sec:>14.0}/s)",
+        elapsed
+    );
+}
+
+/// Text mode renders the template on every update.
+fn text_mode_unthrottled() {
+    const UPDATES: usize = 20_000;
+    set_output(ProgressOutput::Text);
+    let job = ProgressJobBuilder::new()
+        .body("{{ message }}")
+        .prop("message", "unthrottled")
+        .progress_total(UPDATES)
+        .start();
+    let start = Instant::now();
+    for _ in 0..UPDATES {
+        job.increment(black_box(1));
+    }
+    report("text mode, every update rendered", UPDATES, start.elapsed());
+    job.set_status(ProgressStatus::Done);
+    clear_jobs();
+}
+
+/// Text mode with a minimum interval only renders a few times per second.
+fn text_mode_throttled() {
+    const UPDATES: usize = 2_000_000;
+    set_output(ProgressOutput::Text);
+    let job = ProgressJobBuilder::new()
+        .body("{{ message }}")
+        .prop("message", "throttled")
+        .progress_total(UPDATES)
+        .min_update_interval(Duration::from_millis(100))
+        .start();
+    let start = Instant::now();
+    for _ in 0..UPDATES {
+        job.increment(black_box(1));
+    }
+    report(
+        "text mode, min_update_interval=100ms",
+        UPDATES,
+        start.elapsed(),
+    );
+    job.set_status(ProgressStatus::Done);
+    clear_jobs();
+}
+
+/// Many jobs sharing one body render from a single compiled template.
+fn text_mode_shared_body() {
+    const JOBS: usize = 500;
+    const ROUNDS: usize = 20;
+    set_output(ProgressOutput::Text);
+    let jobs = (0..JOBS)
+        .map(|_| {
+            ProgressJobBuilder::new()
+                .body("{{ spinner() }} {{ message }} {{ percentage() }} {{ eta() }}")
+                .prop("message", "shared body")
+                .progress_total(ROUNDS)
+                .start()
+        })
+        .collect::<Vec<_>>();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for job in &jobs {
+            job.increment(black_box(1));
+        }
+    }
+    report(
+        "text mode, 500 jobs sharing a body",
+        JOBS * ROUNDS,
+        start.elapsed(),
+    );
+    for job in &jobs {
+        job.set_status(ProgressStatus::Done);
+    }
+    clear_jobs();
+}
+
+/// UI mode hands updates to the refresh thread, which renders the latest state.
+fn ui_mode_concurrent() {
+    const THREADS: usize = 4;
+    const UPDATES_PER_THREAD: usize = 250_000;
+    set_output(ProgressOutput::UI);
+    let job = ProgressJobBuilder::new()
+        .body("{{ spinner() }} {{ message }} {{ cur }}/{{ total }}")
+        .prop("message", "concurrent")
+        .progress_total(THREADS * UPDATES_PER_THREAD)
+        .start();
+    let start = Instant::now();
+    let handles = (0..THREADS)
+        .map(|_| {
+            let job = Arc::clone(&job);
+            thread::spawn(move || {
+                for _ in 0..UPDATES_PER_THREAD {
+                    job.increment(black_box(1));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    job.set_status(ProgressStatus::Done);
+    stop_clear();
+    clear_jobs();
+    report(
+        "ui mode, 4 threads, coalesced refresh",
+        THREADS * UPDATES_PER_THREAD,
+        elapsed,
+    );
+}
+
+fn main() {
+    text_mode_unthrottled();
+    text_mode_throttled();
+    text_mode_shared_body();
+    ui_mode_concurrent();
+}
@@ -0,0 +1,39 @@
+//! Example demonstrating tracing integration with progress display.
+//!
+//! Run with: cargo run --example tracing_integration --features tracing
+
+use clx::progress::ProgressLayer;
+use std::{thread, time::Duration};
+use tracing::{info, info_span, warn};
+use tracing_subscriber::prelude::*;
+
+fn main() {
+    // Install the progress-aware layer, showing spans as jobs
+    tracing_subscriber::registry()
+        .with(ProgressLayer::new().with_span_jobs(true))
+        .init();
+
+    info!("Starting application");
+
+    {
+        let build = info_span!("Building project");
+        let _build = build.enter();
+
+        for name in ["core", "cli", "web"] {
+            // Each entered span becomes a child job of the enclosing span's job
+            let span = info_span!("compile", message = name, files = 0);
+            let _guard = span.enter();
+            for files in 1..=5 {
+                thread::sleep(Duration::from_millis(150));
+                span.record("files", files);
+            }
+            if name == "web" {
+                warn!(crate_name = name, "unused import");
+            }
+            // Closing the span marks its job as done and removes it
+        }
+    }
+
+    info!("Build complete");
+    clx::progress::stop();
+}
@@ -10,7 +10,9 @@ use thiserror::Error;
 ///
 /// This enum captures all possible errors that can occur when using the clx library,
 /// including I/O errors, template rendering errors, and script execution failures.
+/// New variants may be added in minor releases, so matches need a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// An I/O error occurred (e.g., writing to terminal).
     #[error(transparent)]
@@ -29,9 +31,32 @@ pub enum Error {
     ///
     /// This happens when a Tera template in a progress job body has invalid syntax
     /// or references undefined variables.
+    #[cfg(feature = "tera")]
     #[error(transparent)]
     Tera(#[from] tera::Error),
 
+    /// The built-in template engine could not parse or render a job body.
+    #[error("template error: {0}")]
+    Template(String),
+
+    /// A prompt needed an answer but there is no interactive terminal to ask
+    /// on, and the prompt has no default.
+    #[error("cannot prompt for {0:?}: no interactive terminal")]
+    NotInteractive(String),
+
+    /// A prompt that asks for a choice was given nothing to choose from.
+    #[error("nothing to choose from for {0:?}")]
+    NoItems(String),
+
+    /// A prompt that asks for a choice was given a default index that is not
+    /// one of its items.
+    #[error("default out of range for {0:?}")]
+    InvalidDefault(String),
+
+    /// The user cancelled a prompt with Escape or Ctrl-C.
+    #[error("prompt cancelled: {0:?}")]
+    PromptCancelled(String),
+
     /// A script or command exited with a non-zero status.
     ///
     /// The first field is the script/command name, and the second is the exit status
@@ -81,6 +106,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "tera")]
     #[test]
     fn test_error_from_tera_error() {
         // Create a Tera error by trying to parse invalid template syntax
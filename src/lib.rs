@@ -24,14 +24,16 @@
 //! ## Modules
 //!
 //! - [`progress`] - Hierarchical progress indicators with spinners and templates
-//! - [`osc`] - OSC 9;4 terminal progress bar integration
+//! - [`osc`] - OSC 9;4 terminal progress bar and desktop notification integration
+//! - [`prompt`] - Confirmations, text input, and selections that pause progress
 //! - [`style`] - Color and formatting utilities for terminal output
 //!
 //! ## Features
 //!
 //! - **Progress Jobs** - Create hierarchical progress indicators with animated spinners,
 //!   status tracking, and nested child jobs
-//! - **Template Rendering** - Use Tera templates for customizable progress display
+//! - **Template Rendering** - Use Tera templates (or a built-in engine when the
+//!   `tera` feature is disabled) for customizable progress display
 //! - **OSC Integration** - Automatic progress bar in terminal title bars for supported
 //!   terminals (Ghostty, VS Code, Windows Terminal, VTE-based)
 //! - **Styling** - Color and formatting utilities with automatic terminal detection
@@ -43,4 +45,5 @@ mod error;
 pub mod osc;
 pub mod progress;
 mod progress_bar;
+pub mod prompt;
 pub mod style;
@@ -6,45 +6,85 @@
 //!
 //! - **Ghostty** - Full support
 //! - **VS Code integrated terminal** - Full support
-//! - **Windows Terminal** - Full support
+//! - **Windows Terminal** and the Windows console host - Full support
 //! - **iTerm2** - Full support
+//! - **WezTerm**, **Kitty**, and **Konsole** - Full support
 //! - **VTE-based terminals** (GNOME Terminal, etc.) - Full support
+//! - **ConEmu** - Full support
 //!
 //! The progress indicator is automatically updated based on job progress and will
 //! show different states (normal, error, warning) based on job status.
 //!
 //! # Configuration
 //!
-//! OSC progress is enabled by default. To disable it:
+//! OSC progress is enabled by default. It can be turned off, and on again, at
+//! any time:
 //!
 //! ```rust,no_run
 //! use clx::osc;
 //!
-//! // Must be called before any progress jobs start
 //! osc::configure(false);
 //! ```
 //!
+//! Whether the terminal supports OSC 9;4 is detected from environment
+//! variables. Where detection gets it wrong, `CLX_OSC=1` or `CLX_OSC=0` forces
+//! it, and [`set_support_override`] does the same from code, taking precedence
+//! over the variable.
+//!
+//! Inside tmux or GNU screen, the sequences are wrapped in a DCS passthrough so
+//! that they reach the outer terminal. tmux only forwards them with
+//! `set -g allow-passthrough on`. There, `TERM_PROGRAM` names the multiplexer,
+//! so the outer terminal is detected from variables it sets that the
+//! multiplexer keeps, such as `LC_TERMINAL`, `KITTY_WINDOW_ID`, `WEZTERM_PANE`,
+//! or `WT_SESSION`. These belong to the terminal the session was started in,
+//! so after reattaching from a different terminal, set `CLX_OSC` instead.
+//!
 //! # How It Works
 //!
 //! When progress jobs are running, clx automatically sends OSC 9;4 sequences to
 //! update the terminal's progress indicator. The progress percentage is calculated
 //! from job progress values or estimated from job status.
+//!
+//! # Desktop Notifications
+//!
+//! With [`set_notify_after`], a top-level job that ran for longer than the
+//! threshold sends a notification when it finishes, so a user who switched
+//! away from the terminal learns that it is done. It is sent as OSC 777
+//! (Ghostty, WezTerm, foot, VTE-based terminals) or OSC 9 (iTerm2, Kitty),
+//! which the terminal turns into a desktop notification.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//!
+//! use clx::osc;
+//!
+//! osc::set_notify_after(Some(Duration::from_secs(30)));
+//! ```
+//!
+//! # Terminal Title
+//!
+//! With [`set_title`], the window and tab title (OSC 0) follows the progress
+//! of all jobs, e.g. `[42%] mise install node`. The previous title is saved on
+//! the terminal's title stack and restored by
+//! [`progress::stop`](crate::progress::stop).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
-use std::io::Write;
-use std::sync::OnceLock;
+use console::Term;
 
 /// Global OSC progress enable/disable flag
-static OSC_ENABLED: OnceLock<bool> = OnceLock::new();
+static OSC_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Support set with [`set_support_override`], ahead of `CLX_OSC` and detection.
+static SUPPORT_OVERRIDE: Mutex<Option<bool>> = Mutex::new(None);
 
 /// Configures whether OSC progress sequences are enabled.
 ///
-/// This must be called before any progress jobs are started. Once set, the value
-/// cannot be changed.
-///
-/// # Panics
-///
-/// Panics if called after OSC progress has already been initialized (either by
-/// a previous call to `configure` or by starting a progress job).
+/// May be called at any time, and again to change the setting. Disabling
+/// clears the progress indicator and restores the terminal title if they are
+/// set.
 ///
 /// # Examples
 ///
@@ -55,9 +95,49 @@ static OSC_ENABLED: OnceLock<bool> = OnceLock::new();
 /// osc::configure(false);
 /// ```
 pub fn configure(enabled: bool) {
-    OSC_ENABLED
-        .set(enabled)
-        .expect("OSC_ENABLED already initialized");
+    if !enabled && is_enabled() {
+        crate::progress::clear_osc_progress();
+    }
+    OSC_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Overrides whether the terminal is treated as supporting OSC progress and
+/// desktop notifications, or goes back to detecting it with `None`.
+///
+/// This takes precedence over the `CLX_OSC` environment variable, which in
+/// turn takes precedence over detection. A terminal forced to be supported
+/// but not recognized is sent OSC 9 notifications.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use clx::osc;
+///
+/// // The user's config says their terminal shows OSC 9;4 progress.
+/// osc::set_support_override(Some(true));
+/// ```
+pub fn set_support_override(supported: Option<bool>) {
+    *SUPPORT_OVERRIDE.lock().unwrap() = supported;
+}
+
+/// Returns the support forced by [`set_support_override`] or `CLX_OSC`.
+fn support_override() -> Option<bool> {
+    static ENV_OSC: OnceLock<Option<bool>> = OnceLock::new();
+
+    SUPPORT_OVERRIDE.lock().unwrap().or_else(|| {
+        *ENV_OSC.get_or_init(|| std::env::var("CLX_OSC").ok().and_then(|v| parse_bool(&v)))
+    })
+}
+
+/// Parses `1`/`true` and `0`/`false`; anything else is no override.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" => Some(true),
+        "0" => Some(false),
+        v if v.eq_ignore_ascii_case("true") => Some(true),
+        v if v.eq_ignore_ascii_case("false") => Some(false),
+        _ => None,
+    }
 }
 
 /// Checks if OSC progress is enabled.
@@ -65,7 +145,7 @@ pub fn configure(enabled: bool) {
 /// Returns `true` if OSC progress sequences will be sent to the terminal.
 /// This is `true` by default unless disabled via [`configure`].
 pub(crate) fn is_enabled() -> bool {
-    *OSC_ENABLED.get_or_init(|| true)
+    OSC_ENABLED.load(Ordering::Relaxed)
 }
 
 /// OSC 9;4 progress states for terminal progress indication.
@@ -110,41 +190,249 @@ impl ProgressState {
 
 /// Checks if the current terminal supports OSC 9;4 progress sequences.
 ///
-/// Detection is based on environment variables:
-/// - `TERM_PROGRAM` - Detects Ghostty, VS Code, iTerm, WezTerm, Alacritty
-/// - `WT_SESSION` - Detects Windows Terminal
-/// - `VTE_VERSION` - Detects VTE-based terminals (GNOME Terminal, etc.)
+/// [`set_support_override`] and then `CLX_OSC` take precedence. Otherwise,
+/// support is detected from environment variables by [`detect_osc_9_4`].
 fn terminal_supports_osc_9_4() -> bool {
     static SUPPORTS_OSC_9_4: OnceLock<bool> = OnceLock::new();
 
-    *SUPPORTS_OSC_9_4.get_or_init(|| {
-        // Check TERM_PROGRAM environment variable for terminal detection
-        if let Ok(term_program) = std::env::var("TERM_PROGRAM") {
-            match term_program.as_str() {
-                // Supported terminals
-                "ghostty" => return true,
-                "vscode" => return true,
-                "iTerm.app" => return true,
-                // Unsupported terminals
-                "WezTerm" => return false,
-                "Alacritty" => return false,
-                _ => {}
-            }
-        }
+    if let Some(supported) = support_override() {
+        return supported;
+    }
+    *SUPPORTS_OSC_9_4.get_or_init(|| detect_osc_9_4(|name| std::env::var(name).ok()))
+}
 
-        // Check for Windows Terminal
-        if std::env::var("WT_SESSION").is_ok() {
-            return true;
-        }
+/// Variables set by terminals that support OSC 9;4: Ghostty, WezTerm, Kitty,
+/// Konsole, Windows Terminal, and VTE-based terminals (GNOME Terminal, etc.).
+const OSC_9_4_TERMINAL_VARS: [&str; 6] = [
+    "GHOSTTY_RESOURCES_DIR",
+    "WEZTERM_PANE",
+    "KITTY_WINDOW_ID",
+    "KONSOLE_VERSION",
+    "WT_SESSION",
+    "VTE_VERSION",
+];
 
-        // Check for VTE-based terminals (GNOME Terminal, etc.)
-        if std::env::var("VTE_VERSION").is_ok() {
-            return true;
-        }
+/// Detects OSC 9;4 support from the environment variables read by `var`:
+/// - `TERM_PROGRAM` - Detects Ghostty, VS Code, iTerm, WezTerm, Alacritty
+/// - `LC_TERMINAL` - Detects iTerm
+/// - [`OSC_9_4_TERMINAL_VARS`] - Detect the terminals that set them
+/// - `ConEmuANSI` - Detects ConEmu
+/// - `OS` without `TERM` - Detects the Windows console host
+///
+/// tmux and screen set `TERM_PROGRAM` to their own name, but keep the other
+/// variables of the terminal they were started in, so that terminal is still
+/// detected.
+fn detect_osc_9_4(var: impl Fn(&str) -> Option<String>) -> bool {
+    match var("TERM_PROGRAM").as_deref() {
+        // Supported terminals
+        Some("ghostty" | "vscode" | "iTerm.app" | "WezTerm") => return true,
+        // Unsupported terminals
+        Some("Alacritty") => return false,
+        _ => {}
+    }
+    if var("LC_TERMINAL").as_deref() == Some("iTerm2") {
+        return true;
+    }
+    if OSC_9_4_TERMINAL_VARS.iter().any(|name| var(name).is_some()) {
+        return true;
+    }
+    // ConEmu introduced OSC 9;4
+    if var("ConEmuANSI").as_deref() == Some("ON") {
+        return true;
+    }
+    // The Windows console host sets no `TERM`, unlike MSYS2 and Cygwin
+    // terminals. Unknown terminals default to false to avoid escape sequence
+    // pollution.
+    var("OS").as_deref() == Some("Windows_NT") && var("TERM").is_none()
+}
 
-        // Default to false for unknown terminals to avoid escape sequence pollution
-        false
-    })
+/// How the terminal is sent desktop notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotificationStyle {
+    /// `OSC 9 ; message`, from iTerm2.
+    Osc9,
+    /// `OSC 777 ; notify ; title ; body`, from rxvt-unicode.
+    Osc777,
+}
+
+/// Minimum duration of a top-level job that notifies when it finishes.
+static NOTIFY_AFTER: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Sends a desktop notification when a top-level job that ran for at least
+/// `threshold` finishes, or stops sending them with `None`.
+///
+/// The notification is titled with the job's `message` and says how the job
+/// ended and how long it took. Cancelled jobs do not notify. Nothing is sent
+/// unless OSC sequences are enabled, stderr is a terminal, and the terminal is
+/// one known to show notifications. Off by default.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use clx::osc;
+///
+/// osc::set_notify_after(Some(Duration::from_secs(30)));
+/// ```
+pub fn set_notify_after(threshold: Option<Duration>) {
+    *NOTIFY_AFTER.lock().unwrap() = threshold;
+}
+
+/// Returns the threshold set with [`set_notify_after`].
+pub(crate) fn notify_after() -> Option<Duration> {
+    *NOTIFY_AFTER.lock().unwrap()
+}
+
+/// Returns how the current terminal takes desktop notifications, if it does.
+fn notification_style() -> Option<NotificationStyle> {
+    static NOTIFICATION_STYLE: OnceLock<Option<NotificationStyle>> = OnceLock::new();
+
+    let detected = *NOTIFICATION_STYLE
+        .get_or_init(|| detect_notification_style(|name| std::env::var(name).ok()));
+    match support_override() {
+        Some(false) => None,
+        Some(true) => detected.or(Some(NotificationStyle::Osc9)),
+        None => detected,
+    }
+}
+
+/// Picks the notification sequence from the terminal's environment variables.
+fn detect_notification_style(var: impl Fn(&str) -> Option<String>) -> Option<NotificationStyle> {
+    match var("TERM_PROGRAM").as_deref() {
+        Some("iTerm.app") => return Some(NotificationStyle::Osc9),
+        Some("ghostty" | "WezTerm") => return Some(NotificationStyle::Osc777),
+        _ => {}
+    }
+    // Set by the outer terminal, so also found inside tmux and screen.
+    if var("LC_TERMINAL").as_deref() == Some("iTerm2") || var("KITTY_WINDOW_ID").is_some() {
+        return Some(NotificationStyle::Osc9);
+    }
+    if var("GHOSTTY_RESOURCES_DIR").is_some() || var("WEZTERM_PANE").is_some() {
+        return Some(NotificationStyle::Osc777);
+    }
+    if var("TERM").is_some_and(|term| term.starts_with("foot")) || var("VTE_VERSION").is_some() {
+        return Some(NotificationStyle::Osc777);
+    }
+    // Unknown terminals may print the sequence, so send nothing.
+    None
+}
+
+/// Builds the notification sequence. Escape sequences and control characters
+/// are removed, since they could end the sequence early.
+fn notification_sequence(style: NotificationStyle, title: &str, body: &str) -> String {
+    let clean = |s: &str| {
+        console::strip_ansi_codes(s)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect::<String>()
+    };
+    match style {
+        NotificationStyle::Osc9 => format!("\x1b]9;{}: {}\x1b\\", clean(title), clean(body)),
+        // The title is a `;`-separated field; the body is the last one.
+        NotificationStyle::Osc777 => format!(
+            "\x1b]777;notify;{};{}\x1b\\",
+            clean(title).replace(';', ","),
+            clean(body)
+        ),
+    }
+}
+
+/// Sends a desktop notification if the terminal supports one.
+///
+/// Called by the progress system when a long-running job finishes.
+pub(crate) fn send_notification(title: &str, body: &str) {
+    let term = crate::progress::term();
+    if !is_enabled() || !term.is_term() {
+        return;
+    }
+    if let Some(style) = notification_style() {
+        let _ = write_osc(&term, &notification_sequence(style, title, body));
+    }
+}
+
+/// Template for the terminal title.
+static TITLE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Set when the title template fails to render, so the error is reported once.
+static TITLE_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the terminal window and tab title to a template rendered from the
+/// progress of all jobs, or stops setting it with `None`.
+///
+/// The template is rendered with the default
+/// [`TemplateEngine`](crate::progress::TemplateEngine) whenever the display
+/// refreshes, with these variables:
+///
+/// - `percentage` - Overall progress, as shown by the OSC 9;4 indicator
+/// - `message` - The first top-level job's `message`
+/// - `running` - How many top-level jobs are running
+/// - `failed` - Whether any job has failed
+///
+/// The title is only written when it changes. Before the first write, the
+/// current title is pushed onto the terminal's title stack (CSI 22 t), and
+/// [`progress::stop`](crate::progress::stop) pops it (CSI 23 t) to restore
+/// it. Inside tmux or screen, the title goes to the multiplexer, which shows it
+/// as the pane or window title. A template that fails to render is reported
+/// once and then skipped until the next call to `set_title`.
+/// Nothing is written unless OSC sequences are enabled and stderr is a
+/// terminal.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use clx::osc;
+///
+/// osc::set_title(Some("[{{ percentage }}%] {{ message }}"));
+/// ```
+pub fn set_title<S: Into<String>>(template: Option<S>) {
+    *TITLE.lock().unwrap() = template.map(Into::into);
+    TITLE_FAILED.store(false, Ordering::Relaxed);
+}
+
+/// Returns the template set with [`set_title`], unless it failed to render.
+pub(crate) fn title_template() -> Option<String> {
+    if TITLE_FAILED.load(Ordering::Relaxed) {
+        return None;
+    }
+    TITLE.lock().unwrap().clone()
+}
+
+/// Skips the title template until [`set_title`] is called again.
+pub(crate) fn title_failed() {
+    TITLE_FAILED.store(true, Ordering::Relaxed);
+}
+
+/// Saves the current title on the terminal's title stack.
+pub(crate) fn push_title() {
+    write_title_sequence("\x1b[22;0t");
+}
+
+/// Sets the window and tab title.
+pub(crate) fn set_title_text(title: &str) {
+    write_title_sequence(&title_sequence(title));
+}
+
+/// Restores the title saved by [`push_title`].
+pub(crate) fn pop_title() {
+    write_title_sequence("\x1b[23;0t");
+}
+
+fn title_sequence(title: &str) -> String {
+    let title = console::strip_ansi_codes(title)
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    format!("\x1b]0;{title}\x1b\\")
+}
+
+/// Writes a title sequence. Callers check [`is_enabled`] before setting a
+/// title, but a saved title is restored even if OSC was disabled since.
+fn write_title_sequence(sequence: &str) {
+    let term = crate::progress::term();
+    if term.is_term() {
+        let _ = term.write_str(sequence);
+    }
 }
 
 /// Sends an OSC 9;4 sequence to set terminal progress.
@@ -163,8 +451,11 @@ pub(crate) fn set_progress(state: ProgressState, progress: u8) {
 }
 
 fn write_progress(state: ProgressState, progress: u8) -> std::io::Result<()> {
-    // Only write OSC sequences if enabled and stderr is actually a terminal
-    if !is_enabled() || !console::user_attended_stderr() {
+    // Only write OSC sequences if enabled and stderr is actually a terminal.
+    // This is the progress terminal rather than fd 2, which output capture
+    // may have redirected.
+    let term = crate::progress::term();
+    if !is_enabled() || !term.is_term() {
         return Ok(());
     }
 
@@ -173,12 +464,58 @@ fn write_progress(state: ProgressState, progress: u8) -> std::io::Result<()> {
         return Ok(());
     }
 
-    let mut stderr = std::io::stderr();
     // OSC 9;4 format: ESC ] 9 ; 4 ; <state> ; <progress> BEL
     // Note: The color is controlled by the terminal theme
     // Ghostty may show cyan automatically for normal progress
-    write!(stderr, "\x1b]9;4;{};{}\x1b\\", state.as_code(), progress)?;
-    stderr.flush()
+    write_osc(
+        &term,
+        &format!("\x1b]9;4;{};{}\x1b\\", state.as_code(), progress),
+    )
+}
+
+/// A terminal multiplexer that needs OSC sequences passed through to the
+/// outer terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+fn multiplexer() -> Option<Multiplexer> {
+    static MULTIPLEXER: OnceLock<Option<Multiplexer>> = OnceLock::new();
+
+    *MULTIPLEXER.get_or_init(|| detect_multiplexer(|name| std::env::var(name).ok()))
+}
+
+fn detect_multiplexer(var: impl Fn(&str) -> Option<String>) -> Option<Multiplexer> {
+    if var("TMUX").is_some() {
+        Some(Multiplexer::Tmux)
+    } else if var("STY").is_some() {
+        Some(Multiplexer::Screen)
+    } else {
+        None
+    }
+}
+
+/// Wraps an ST-terminated OSC sequence in a DCS passthrough for `multiplexer`.
+fn passthrough(sequence: &str, multiplexer: Option<Multiplexer>) -> String {
+    match multiplexer {
+        None => sequence.to_string(),
+        // tmux unwraps doubled escapes.
+        Some(Multiplexer::Tmux) => {
+            format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+        }
+        // screen ends the passthrough at the first ST, so the OSC ends with BEL.
+        Some(Multiplexer::Screen) => {
+            let osc = sequence.strip_suffix("\x1b\\").unwrap_or(sequence);
+            format!("\x1bP{osc}\x07\x1b\\")
+        }
+    }
+}
+
+/// Writes an OSC sequence meant for the outer terminal.
+fn write_osc(term: &Term, sequence: &str) -> std::io::Result<()> {
+    term.write_str(&passthrough(sequence, multiplexer()))
 }
 
 /// Clears any terminal progress indicator.
@@ -190,6 +527,15 @@ pub(crate) fn clear_progress() {
     }
 }
 
+/// Serializes the tests that change OSC detection or write OSC sequences, so
+/// one test's override or `configure` call cannot change what another emits.
+#[cfg(test)]
+pub(crate) fn test_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,6 +551,7 @@ mod tests {
 
     #[test]
     fn test_set_progress_doesnt_panic() {
+        let _lock = test_lock();
         // Just ensure it doesn't panic when called
         set_progress(ProgressState::Normal, 50);
         set_progress(ProgressState::Indeterminate, 0);
@@ -213,6 +560,7 @@ mod tests {
 
     #[test]
     fn test_progress_clamping() {
+        let _lock = test_lock();
         // Verify that progress values over 100 are clamped
         set_progress(ProgressState::Normal, 150);
     }
@@ -247,6 +595,7 @@ mod tests {
 
     #[test]
     fn test_progress_boundary_values() {
+        let _lock = test_lock();
         // Test boundary values for progress percentage
         set_progress(ProgressState::Normal, 0);
         set_progress(ProgressState::Normal, 100);
@@ -255,6 +604,7 @@ mod tests {
 
     #[test]
     fn test_all_progress_states() {
+        let _lock = test_lock();
         // Ensure all states can be used with set_progress
         for state in [
             ProgressState::None,
@@ -267,8 +617,148 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_detect_osc_9_4() {
+        let detect = |vars: &[(&str, &str)]| {
+            detect_osc_9_4(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert!(detect(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(detect(&[("TERM", "xterm-kitty"), ("KITTY_WINDOW_ID", "1")]));
+        assert!(detect(&[("KONSOLE_VERSION", "250400")]));
+        assert!(detect(&[("OS", "Windows_NT")]));
+        assert!(!detect(&[("OS", "Windows_NT"), ("TERM", "xterm-256color")]));
+        assert!(!detect(&[("TERM_PROGRAM", "Alacritty")]));
+        assert!(!detect(&[("TERM_PROGRAM", "Apple_Terminal")]));
+        assert!(!detect(&[]));
+        // Inside tmux, the outer terminal is found from the variables it set.
+        let tmux = [("TERM_PROGRAM", "tmux"), ("TMUX", "/tmp/tmux-1000/default")];
+        assert!(!detect(&tmux));
+        assert!(detect(&[tmux[0], tmux[1], ("LC_TERMINAL", "iTerm2")]));
+        assert!(detect(&[tmux[0], tmux[1], ("WT_SESSION", "1")]));
+    }
+
+    #[test]
+    fn test_detect_notification_style() {
+        let detect = |vars: &[(&str, &str)]| {
+            detect_notification_style(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app")]),
+            Some(NotificationStyle::Osc9)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "ghostty")]),
+            Some(NotificationStyle::Osc777)
+        );
+        assert_eq!(
+            detect(&[("TERM", "xterm-kitty"), ("KITTY_WINDOW_ID", "1")]),
+            Some(NotificationStyle::Osc9)
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), Some(NotificationStyle::Osc777));
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "tmux"), ("LC_TERMINAL", "iTerm2")]),
+            Some(NotificationStyle::Osc9)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "tmux"), ("WEZTERM_PANE", "0")]),
+            Some(NotificationStyle::Osc777)
+        );
+        assert_eq!(detect(&[("TERM_PROGRAM", "Apple_Terminal")]), None);
+        assert_eq!(detect(&[]), None);
+    }
+
+    #[test]
+    fn test_notification_sequence() {
+        assert_eq!(
+            notification_sequence(NotificationStyle::Osc9, "install", "Done in 42s"),
+            "\x1b]9;install: Done in 42s\x1b\\"
+        );
+        assert_eq!(
+            notification_sequence(
+                NotificationStyle::Osc777,
+                "\x1b[1mbuild; test\x1b[0m",
+                "Failed\x07 after 1m3s"
+            ),
+            "\x1b]777;notify;build, test;Failed after 1m3s\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_title_sequence() {
+        assert_eq!(
+            title_sequence("\x1b[32m[42%]\x1b[0m install\x07 node"),
+            "\x1b]0;[42%] install node\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("1"), Some(true));
+        assert_eq!(parse_bool("TRUE"), Some(true));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("false"), Some(false));
+        assert_eq!(parse_bool(""), None);
+        assert_eq!(parse_bool("auto"), None);
+    }
+
+    #[test]
+    fn test_support_override() {
+        let _lock = test_lock();
+        set_support_override(Some(true));
+        assert!(terminal_supports_osc_9_4());
+        set_support_override(Some(false));
+        assert!(!terminal_supports_osc_9_4());
+        assert_eq!(notification_style(), None);
+        set_support_override(None);
+    }
+
+    #[test]
+    fn test_configure_can_be_called_again() {
+        let _lock = test_lock();
+        configure(false);
+        assert!(!is_enabled());
+        configure(true);
+        assert!(is_enabled());
+    }
+
+    #[test]
+    fn test_detect_multiplexer() {
+        assert_eq!(
+            detect_multiplexer(|name| (name == "TMUX").then(|| "/tmp/tmux-1000/default".into())),
+            Some(Multiplexer::Tmux)
+        );
+        assert_eq!(
+            detect_multiplexer(|name| (name == "STY").then(|| "1234.pts-0.host".into())),
+            Some(Multiplexer::Screen)
+        );
+        assert_eq!(detect_multiplexer(|_| None), None);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let osc = "\x1b]9;4;1;50\x1b\\";
+        assert_eq!(passthrough(osc, None), osc);
+        assert_eq!(
+            passthrough(osc, Some(Multiplexer::Tmux)),
+            "\x1bPtmux;\x1b\x1b]9;4;1;50\x1b\x1b\\\x1b\\"
+        );
+        assert_eq!(
+            passthrough(osc, Some(Multiplexer::Screen)),
+            "\x1bP\x1b]9;4;1;50\x07\x1b\\"
+        );
+    }
+
     #[test]
     fn test_clear_progress_idempotent() {
+        let _lock = test_lock();
         // Clearing progress multiple times should not panic
         clear_progress();
         clear_progress();
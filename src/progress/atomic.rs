@@ -0,0 +1,135 @@
+//! Lock-free storage for progress counters.
+//!
+//! Worker threads update these on every `increment()`, so they are kept in
+//! atomics rather than behind a `Mutex` each.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// An `Option<usize>` stored in a single `AtomicUsize`.
+///
+/// `usize::MAX` encodes `None`, so stored values saturate at `usize::MAX - 1`.
+pub(crate) struct AtomicOptionUsize(AtomicUsize);
+
+impl AtomicOptionUsize {
+    const NONE: usize = usize::MAX;
+
+    pub(crate) fn new(value: Option<usize>) -> Self {
+        Self(AtomicUsize::new(Self::encode(value)))
+    }
+
+    pub(crate) fn load(&self) -> Option<usize> {
+        Self::decode(self.0.load(Ordering::Acquire))
+    }
+
+    pub(crate) fn store(&self, value: Option<usize>) {
+        self.0.store(Self::encode(value), Ordering::Release);
+    }
+
+    /// Atomically replaces the value with `f(current)`, retrying if another
+    /// thread changed it in between. Returns the value that was stored.
+    pub(crate) fn update(
+        &self,
+        mut f: impl FnMut(Option<usize>) -> Option<usize>,
+    ) -> Option<usize> {
+        let mut current = self.0.load(Ordering::Acquire);
+        loop {
+            let new = Self::encode(f(Self::decode(current)));
+            match self
+                .0
+                .compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Self::decode(new),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn encode(value: Option<usize>) -> usize {
+        value.map_or(Self::NONE, |v| v.min(Self::NONE - 1))
+    }
+
+    fn decode(raw: usize) -> Option<usize> {
+        (raw != Self::NONE).then_some(raw)
+    }
+}
+
+/// An `Option<f64>` stored as its bit pattern in an `AtomicU64`.
+///
+/// `u64::MAX` (a NaN payload no arithmetic produces) encodes `None`.
+pub(crate) struct AtomicOptionF64(AtomicU64);
+
+impl AtomicOptionF64 {
+    const NONE: u64 = u64::MAX;
+
+    pub(crate) fn new(value: Option<f64>) -> Self {
+        Self(AtomicU64::new(Self::encode(value)))
+    }
+
+    pub(crate) fn load(&self) -> Option<f64> {
+        let raw = self.0.load(Ordering::Acquire);
+        (raw != Self::NONE).then(|| f64::from_bits(raw))
+    }
+
+    pub(crate) fn store(&self, value: Option<f64>) {
+        self.0.store(Self::encode(value), Ordering::Release);
+    }
+
+    fn encode(value: Option<f64>) -> u64 {
+        value.map_or(Self::NONE, f64::to_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_option_usize_round_trip() {
+        let value = AtomicOptionUsize::new(None);
+        assert_eq!(value.load(), None);
+
+        value.store(Some(0));
+        assert_eq!(value.load(), Some(0));
+
+        value.store(Some(usize::MAX));
+        assert_eq!(value.load(), Some(usize::MAX - 1));
+
+        assert_eq!(value.update(|_| Some(5)), Some(5));
+        assert_eq!(value.update(|v| v.map(|v| v + 1)), Some(6));
+        assert_eq!(value.update(|_| None), None);
+    }
+
+    #[test]
+    fn test_atomic_option_usize_concurrent_update() {
+        let value = std::sync::Arc::new(AtomicOptionUsize::new(None));
+        let handles = (0..8)
+            .map(|_| {
+                let value = value.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        value.update(|v| Some(v.unwrap_or(0) + 1));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(value.load(), Some(8000));
+    }
+
+    #[test]
+    fn test_atomic_option_f64_round_trip() {
+        let value = AtomicOptionF64::new(None);
+        assert_eq!(value.load(), None);
+
+        value.store(Some(42.5));
+        assert_eq!(value.load(), Some(42.5));
+
+        value.store(Some(0.0));
+        assert_eq!(value.load(), Some(0.0));
+
+        value.store(None);
+        assert_eq!(value.load(), None);
+    }
+}
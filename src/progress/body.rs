@@ -0,0 +1,485 @@
+//! Typed builder for job body templates.
+
+use std::fmt;
+
+use crate::{Error, Result};
+
+use super::spinners::SPINNERS;
+
+/// Preset characters for [`Body::bar`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BarStyle {
+    /// `[===>   ]`
+    #[default]
+    Default,
+    /// `███▓░░░`
+    Blocks,
+    /// `━━━╸───`
+    Thin,
+}
+
+/// Color and style filters for [`Body::style`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    Cyan,
+    Blue,
+    Green,
+    Yellow,
+    Red,
+    Magenta,
+    Bold,
+    Dim,
+    Underline,
+}
+
+impl TextStyle {
+    fn filter(self) -> &'static str {
+        match self {
+            TextStyle::Cyan => "cyan",
+            TextStyle::Blue => "blue",
+            TextStyle::Green => "green",
+            TextStyle::Yellow => "yellow",
+            TextStyle::Red => "red",
+            TextStyle::Magenta => "magenta",
+            TextStyle::Bold => "bold",
+            TextStyle::Dim => "dim",
+            TextStyle::Underline => "underline",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Text(String),
+    Prop(String),
+    Function(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    term: Term,
+    args: Vec<(&'static str, String)>,
+    filters: Vec<&'static str>,
+}
+
+/// Builds a job body from typed parts instead of a template string.
+///
+/// Parts are separated by a single space. Modifiers such as [`flex_fill`]
+/// and [`style`] apply to the part added just before them. [`build`] returns
+/// the template string, which renders with either
+/// [`TemplateEngine`](super::TemplateEngine).
+///
+/// A `Body` can also be passed to
+/// [`ProgressJobBuilder::body`](super::ProgressJobBuilder::body) as it is. An
+/// invalid part is then reported by
+/// [`try_build`](super::ProgressJobBuilder::try_build), or, with `build`,
+/// when the job renders, like an invalid template string.
+///
+/// ```rust,no_run
+/// use clx::progress::{BarStyle, Body, ProgressJobBuilder, TextStyle};
+///
+/// let body = Body::new()
+///     .spinner()
+///     .prop("message")
+///     .style(TextStyle::Cyan)
+///     .flex_fill()
+///     .bar(BarStyle::Thin);
+/// let job = ProgressJobBuilder::new()
+///     .body(body)
+///     .prop("message", "Downloading")
+///     .progress_total(100)
+///     .try_start()?;
+/// # Ok::<(), clx::Error>(())
+/// ```
+///
+/// [`flex_fill`]: Body::flex_fill
+/// [`style`]: Body::style
+/// [`build`]: Body::build
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[must_use]
+pub struct Body {
+    segments: Vec<Segment>,
+    /// The first part that cannot be written as a template, reported by
+    /// `build`.
+    error: Option<String>,
+}
+
+impl Body {
+    /// Creates an empty body.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `error` unless an earlier part already failed.
+    fn fail(mut self, error: String) -> Self {
+        self.error.get_or_insert(error);
+        self
+    }
+
+    fn push(mut self, term: Term) -> Self {
+        self.segments.push(Segment {
+            term,
+            args: vec![],
+            filters: vec![],
+        });
+        self
+    }
+
+    fn arg(mut self, name: &'static str, value: String) -> Self {
+        if let Some(segment) = self.segments.last_mut()
+            && matches!(segment.term, Term::Function(_))
+        {
+            segment.args.push((name, value));
+        }
+        self
+    }
+
+    fn filter(mut self, filter: &'static str) -> Self {
+        if let Some(segment) = self.segments.last_mut() {
+            if let Term::Text(text) = &segment.term
+                && quote(text).is_none()
+            {
+                let error = format!("cannot quote text {text:?}");
+                return self.fail(error);
+            }
+            segment.filters.push(filter);
+        }
+        self
+    }
+
+    /// Adds literal text.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.push(Term::Text(text.into()))
+    }
+
+    /// Adds the value of a job property, as set with `prop()`.
+    ///
+    /// If `name` is not a valid identifier, [`build`](Body::build) fails.
+    pub fn prop(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let mut chars = name.chars();
+        if !(chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_'))
+        {
+            return self.fail(format!("invalid property name: {name:?}"));
+        }
+        self.push(Term::Prop(name))
+    }
+
+    /// Adds the status icon, animated with the default spinner while running.
+    pub fn spinner(self) -> Self {
+        self.push(Term::Function("spinner"))
+    }
+
+    /// Adds the status icon, animated with the named spinner while running.
+    ///
+    /// If `name` is not one of the built-in spinners, [`build`](Body::build)
+    /// fails.
+    pub fn spinner_named(self, name: &str) -> Self {
+        if !SPINNERS.contains_key(name) {
+            return self.fail(format!("unknown spinner {name:?}"));
+        }
+        match quote(name) {
+            Some(quoted) => self.spinner().arg("name", quoted),
+            None => self.fail(format!("cannot quote spinner name {name:?}")),
+        }
+    }
+
+    /// Adds a progress bar that fills the available width.
+    pub fn bar(self, style: BarStyle) -> Self {
+        self.push(Term::Function("progress_bar"))
+            .arg("flex", "true".to_string())
+            .bar_style(style)
+    }
+
+    /// Adds a progress bar of a fixed width.
+    pub fn bar_width(self, style: BarStyle, width: usize) -> Self {
+        self.push(Term::Function("progress_bar"))
+            .arg("width", width.to_string())
+            .bar_style(style)
+    }
+
+    fn bar_style(self, style: BarStyle) -> Self {
+        match style {
+            BarStyle::Default => self,
+            BarStyle::Blocks => self.arg("style", "'blocks'".to_string()),
+            BarStyle::Thin => self.arg("style", "'thin'".to_string()),
+        }
+    }
+
+    /// Adds the time since the job started, which stops counting once the
+    /// job finishes.
+    pub fn elapsed(self) -> Self {
+        self.push(Term::Function("elapsed"))
+    }
+
+    /// Adds the time the job took, or nothing while it runs.
+    pub fn duration(self) -> Self {
+        self.push(Term::Function("duration"))
+    }
+
+    /// Adds the estimated time remaining.
+    pub fn eta(self) -> Self {
+        self.push(Term::Function("eta"))
+    }
+
+    /// Adds the throughput rate.
+    pub fn rate(self) -> Self {
+        self.push(Term::Function("rate"))
+    }
+
+    /// Adds the progress as a percentage.
+    pub fn percentage(self) -> Self {
+        self.push(Term::Function("percentage"))
+    }
+
+    /// Adds the progress as human-readable bytes.
+    pub fn bytes(self) -> Self {
+        self.push(Term::Function("bytes"))
+    }
+
+    /// Adds the current progress as a human-readable count.
+    pub fn count(self) -> Self {
+        self.push(Term::Function("count_format"))
+    }
+
+    /// Hides the preceding progress part once progress reaches 100%.
+    ///
+    /// Applies to [`bar`](Body::bar), [`bar_width`](Body::bar_width),
+    /// [`eta`](Body::eta), [`percentage`](Body::percentage), and
+    /// [`bytes`](Body::bytes); after any other part it does nothing.
+    pub fn hide_complete(self) -> Self {
+        match self.segments.last().map(|segment| &segment.term) {
+            Some(Term::Function("progress_bar" | "eta" | "percentage" | "bytes")) => {
+                self.arg("hide_complete", "true".to_string())
+            }
+            _ => self,
+        }
+    }
+
+    /// Truncates the preceding part to fit the available width.
+    ///
+    /// If the preceding part is text containing all three of `'`, `"` and
+    /// `` ` ``, [`build`](Body::build) fails, since filtered text becomes a
+    /// string literal.
+    pub fn flex(self) -> Self {
+        self.filter("flex")
+    }
+
+    /// Pads the preceding part to fill the available width.
+    ///
+    /// Fails under the same condition as [`flex`](Body::flex).
+    pub fn flex_fill(self) -> Self {
+        self.filter("flex_fill")
+    }
+
+    /// Applies a color or style to the preceding part.
+    ///
+    /// Fails under the same condition as [`flex`](Body::flex).
+    pub fn style(self, style: TextStyle) -> Self {
+        self.filter(style.filter())
+    }
+
+    /// Returns the template string for
+    /// [`ProgressJobBuilder::body`](super::ProgressJobBuilder::body).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Template`] for the first part that cannot be written
+    /// as a template: a property name that is not an identifier, an unknown
+    /// spinner name, or filtered text that cannot be quoted.
+    pub fn build(self) -> Result<String> {
+        self.template().map_err(Error::Template)
+    }
+
+    /// Returns the template string, or the message for the first invalid part.
+    fn template(self) -> std::result::Result<String, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self
+                .segments
+                .iter()
+                .map(Segment::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")),
+        }
+    }
+}
+
+/// A job body accepted by
+/// [`ProgressJobBuilder::body`](super::ProgressJobBuilder::body): a template
+/// string, or a [`Body`].
+///
+/// This trait is sealed and cannot be implemented outside of clx.
+pub trait IntoBody: sealed::Sealed {
+    /// Returns the template string, or the message for a body that cannot be
+    /// written as one.
+    #[doc(hidden)]
+    fn into_template(self) -> std::result::Result<String, String>;
+}
+
+impl<S: Into<String>> IntoBody for S {
+    fn into_template(self) -> std::result::Result<String, String> {
+        Ok(self.into())
+    }
+}
+
+impl IntoBody for Body {
+    fn into_template(self) -> std::result::Result<String, String> {
+        self.template()
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl<S: Into<String>> Sealed for S {}
+    impl Sealed for super::Body {}
+}
+
+/// Quotes a template string literal with a quote character it does not
+/// contain, or returns `None` if it contains all of them; neither engine
+/// supports escapes.
+fn quote(s: &str) -> Option<String> {
+    ['\'', '"', '`']
+        .into_iter()
+        .find(|q| !s.contains(*q))
+        .map(|q| format!("{q}{s}{q}"))
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.term {
+            // Unfiltered text is written as-is, with any braces quoted so they
+            // cannot start a tag.
+            Term::Text(text) if self.filters.is_empty() => {
+                return write!(f, "{}", text.replace('{', "{{ '{' }}"));
+            }
+            // `filter()` only accepts text that can be quoted.
+            Term::Text(text) => write!(f, "{{{{ {}", quote(text).unwrap_or_default())?,
+            Term::Prop(name) => write!(f, "{{{{ {name}")?,
+            Term::Function(name) => {
+                let args = self
+                    .args
+                    .iter()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{{{{ {name}({args})")?;
+            }
+        }
+        for filter in &self.filters {
+            write!(f, " | {filter}")?;
+        }
+        write!(f, " }}}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::functions::RenderState;
+    use crate::progress::simple::Template;
+    use crate::progress::template::Context;
+
+    #[test]
+    fn test_body_builds_template() {
+        let body = Body::new()
+            .spinner()
+            .prop("message")
+            .style(TextStyle::Cyan)
+            .flex_fill()
+            .bar(BarStyle::Thin)
+            .hide_complete();
+        assert_eq!(
+            body.build().unwrap(),
+            "{{ spinner() }} {{ message | cyan | flex_fill }} \
+             {{ progress_bar(flex=true, style='thin', hide_complete=true) }}"
+        );
+    }
+
+    #[test]
+    fn test_body_text_is_literal() {
+        let body = Body::new()
+            .text("{{ not a tag }}")
+            .text("it's \"quoted\"")
+            .style(TextStyle::Bold);
+        let mut ctx = Context::new();
+        ctx.insert("message", "");
+        let rendered = Template::parse(&body.build().unwrap())
+            .unwrap()
+            .render(&ctx, &RenderState::default())
+            .unwrap();
+        assert_eq!(
+            rendered,
+            format!(
+                "{{{{ not a tag }}}} {}",
+                crate::style::ebold("it's \"quoted\"")
+            )
+        );
+    }
+
+    #[cfg(feature = "tera")]
+    #[test]
+    fn test_body_renders_the_same_with_tera() {
+        use crate::progress::TemplateEngine;
+        use crate::progress::template::Templates;
+
+        let body = Body::new()
+            .text("{ it's `odd` }")
+            .style(TextStyle::Dim)
+            .text("{x}")
+            .percentage()
+            .hide_complete()
+            .bar_width(BarStyle::Blocks, 10)
+            .build()
+            .unwrap();
+        let state = RenderState {
+            progress: Some((1, 2)),
+            width: 80,
+            ..Default::default()
+        };
+        let mut templates = Templates::new();
+        let mut render = |engine| {
+            let mut ctx = Context::new();
+            templates.render(engine, &body, &mut ctx, &state).unwrap()
+        };
+        assert_eq!(render(TemplateEngine::Simple), render(TemplateEngine::Tera));
+    }
+
+    #[test]
+    fn test_body_rejects_invalid_prop_names() {
+        let err = Body::new().prop("not valid").spinner().build().unwrap_err();
+        assert!(matches!(err, Error::Template(msg) if msg.contains("invalid property name")));
+    }
+
+    #[test]
+    fn test_hide_complete_only_applies_to_progress_parts() {
+        let body = Body::new()
+            .text("done")
+            .hide_complete()
+            .spinner()
+            .hide_complete();
+        assert_eq!(body.build().unwrap(), "done {{ spinner() }}");
+    }
+
+    #[test]
+    fn test_body_rejects_unquotable_filtered_text() {
+        let err = Body::new()
+            .text("'\"`")
+            .style(TextStyle::Bold)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::Template(msg) if msg.contains("cannot quote text")));
+        assert!(Body::new().spinner_named("'\"`").build().is_err());
+    }
+
+    #[test]
+    fn test_body_rejects_unknown_spinner_names() {
+        let err = Body::new().spinner_named("typo").build().unwrap_err();
+        assert!(matches!(err, Error::Template(msg) if msg.contains("unknown spinner")));
+        assert_eq!(
+            Body::new().spinner_named("dot").build().unwrap(),
+            "{{ spinner(name='dot') }}"
+        );
+    }
+}
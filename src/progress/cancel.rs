@@ -0,0 +1,78 @@
+//! Cancellation tokens shared by a job and its descendants.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// A flag that work can poll to find out it should stop.
+///
+/// Every [`ProgressJob`](super::ProgressJob) has a token, and a child job's
+/// token is linked to its parent's, so cancelling a job also cancels everything
+/// below it. Clones share the same flag, which makes a token cheap to hand to
+/// worker threads.
+///
+/// ```rust
+/// use clx::progress::CancellationToken;
+///
+/// let parent = CancellationToken::new();
+/// let child = parent.child();
+/// parent.cancel();
+/// assert!(child.is_cancelled());
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    parent: OnceLock<CancellationToken>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that is cancelled when this one is.
+    ///
+    /// Cancelling the child does not cancel this token.
+    #[must_use]
+    pub fn child(&self) -> Self {
+        let child = Self::new();
+        child.link(self);
+        child
+    }
+
+    /// Cancels this token and every token created from it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if this token or one of its ancestors was cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+            || self
+                .inner
+                .parent
+                .get()
+                .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Links a job's token to its parent job's once the job is added.
+    pub(crate) fn link(&self, parent: &CancellationToken) {
+        let _ = self.inner.parent.set(parent.clone());
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
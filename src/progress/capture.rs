@@ -0,0 +1,215 @@
+//! Capture of stdout/stderr writes made outside of clx.
+//!
+//! Code that writes to the terminal directly, such as a dependency calling
+//! `println!`, lands in the middle of the live frame and is overwritten by the
+//! next redraw. Capture points fds 1 and 2 at pipes instead. Reader threads
+//! pass each complete line to the original descriptor with the frame cleared,
+//! the same way [`ProgressJob::println`](super::ProgressJob::println) does.
+//! The renderer keeps writing to the original stderr.
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::{AsFd, OwnedFd};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console::Term;
+use nix::unistd::{dup2_stderr, dup2_stdout};
+
+use crate::Result;
+
+use super::job::write_above_frame;
+use super::state::{TERM_LOCK, set_term};
+
+/// How long [`release_output`] waits for captured output to be written.
+///
+/// Readers only see the end of the pipe once every copy of the write end is
+/// closed, which a child process that inherited it may delay indefinitely.
+const RELEASE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Saved descriptors and reader threads of the active capture.
+struct Capture {
+    /// Original stdout, if it was captured.
+    stdout: Option<OwnedFd>,
+    /// Original stderr, if it was captured.
+    stderr: Option<OwnedFd>,
+    /// Signalled by each reader once it has written everything it read.
+    done: Receiver<()>,
+    readers: usize,
+}
+
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Redirects the process's stdout and stderr through the progress display.
+///
+/// While capturing, anything written to fd 1 or fd 2, including output from
+/// `println!`, `eprintln!`, and C libraries, is printed above the live frame
+/// line by line instead of corrupting it. A trailing partial line is held
+/// until its newline arrives or capture is released. Only descriptors
+/// connected to a terminal are captured; redirected output is left alone.
+///
+/// Capture is released by [`release_output`], [`stop`](super::stop), and
+/// [`stop_clear`](super::stop_clear). Calling this while already capturing
+/// does nothing.
+///
+/// Child processes spawned while capturing inherit the pipes, so their output
+/// is captured as well.
+///
+/// # Errors
+///
+/// Returns an error if the pipes cannot be created or the descriptors cannot
+/// be redirected. Nothing is captured in that case.
+///
+/// ```rust,no_run
+/// use clx::progress::{ProgressJobBuilder, ProgressStatus, capture_output, stop};
+///
+/// capture_output().unwrap();
+/// let job = ProgressJobBuilder::new().prop("message", "Working").start();
+/// println!("printed above the progress display");
+/// job.set_status(ProgressStatus::Done);
+/// stop(); // restores stdout and stderr
+/// ```
+pub fn capture_output() -> Result<()> {
+    let mut capture = CAPTURE.lock().unwrap();
+    if capture.is_some() {
+        return Ok(());
+    }
+    let capture_stdout = io::stdout().is_terminal();
+    let capture_stderr = io::stderr().is_terminal();
+    if !capture_stdout && !capture_stderr {
+        return Ok(());
+    }
+
+    let saved_stdout = capture_stdout
+        .then(|| io::stdout().as_fd().try_clone_to_owned())
+        .transpose()?;
+    let saved_stderr = capture_stderr
+        .then(|| io::stderr().as_fd().try_clone_to_owned())
+        .transpose()?;
+    let stdout_pipe = capture_stdout.then(io::pipe).transpose()?;
+    let stderr_pipe = capture_stderr.then(io::pipe).transpose()?;
+
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    {
+        // Hold the terminal lock so no frame is half-written to each side.
+        let _guard = TERM_LOCK.lock().unwrap();
+        if let Some(saved) = &saved_stderr {
+            let original = File::from(saved.try_clone()?);
+            set_term(Term::read_write_pair(original.try_clone()?, original));
+        }
+        let mut redirected = Ok(());
+        if let Some((_, writer)) = &stdout_pipe {
+            redirected = dup2_stdout(writer);
+        }
+        if redirected.is_ok()
+            && let Some((_, writer)) = &stderr_pipe
+        {
+            redirected = dup2_stderr(writer);
+        }
+        if let Err(err) = redirected {
+            restore(saved_stdout.as_ref(), saved_stderr.as_ref());
+            set_term(Term::stderr());
+            return Err(err.into());
+        }
+    }
+
+    let (tx, done) = mpsc::channel();
+    let mut readers = 0;
+    for (pipe, saved) in [(stdout_pipe, &saved_stdout), (stderr_pipe, &saved_stderr)] {
+        let (Some((reader, _writer)), Some(saved)) = (pipe, saved) else {
+            continue;
+        };
+        // `_writer` is dropped here; fd 1 or 2 now holds the only write end.
+        let original = File::from(saved.try_clone()?);
+        let tx = tx.clone();
+        readers += 1;
+        thread::Builder::new()
+            .name("clx-capture".into())
+            .spawn(move || {
+                forward_lines(reader, original);
+                let _ = tx.send(());
+            })?;
+    }
+
+    *capture = Some(Capture {
+        stdout: saved_stdout,
+        stderr: saved_stderr,
+        done,
+        readers,
+    });
+    Ok(())
+}
+
+/// Restores stdout and stderr redirected by [`capture_output`].
+///
+/// Output already captured is printed first. Does nothing if output is not
+/// being captured.
+pub fn release_output() {
+    let Some(capture) = CAPTURE.lock().unwrap().take() else {
+        return;
+    };
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    {
+        let _guard = TERM_LOCK.lock().unwrap();
+        // Replacing fds 1 and 2 closes the write ends of the pipes, so the
+        // readers see the end of their input once they have drained it.
+        restore(capture.stdout.as_ref(), capture.stderr.as_ref());
+        set_term(Term::stderr());
+    }
+    let deadline = Instant::now() + RELEASE_TIMEOUT;
+    for _ in 0..capture.readers {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if capture.done.recv_timeout(timeout).is_err() {
+            break;
+        }
+    }
+}
+
+/// Returns `true` while stdout or stderr is being captured.
+#[must_use]
+pub fn is_capturing_output() -> bool {
+    CAPTURE.lock().unwrap().is_some()
+}
+
+fn restore(stdout: Option<&OwnedFd>, stderr: Option<&OwnedFd>) {
+    if let Some(fd) = stdout {
+        let _ = dup2_stdout(fd);
+    }
+    if let Some(fd) = stderr {
+        let _ = dup2_stderr(fd);
+    }
+}
+
+/// Copies complete lines from `reader` to `original` above the progress
+/// frame until the pipe is closed, then writes any partial line left over.
+fn forward_lines(mut reader: impl Read, original: File) {
+    let original = Arc::new(original);
+    let mut pending = Vec::new();
+    let mut chunk = [0; 8192];
+    loop {
+        let count = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        pending.extend_from_slice(&chunk[..count]);
+        if let Some(end) = pending.iter().rposition(|&b| b == b'\n') {
+            let lines: Vec<u8> = pending.drain(..=end).collect();
+            let original = original.clone();
+            write_above_frame(move || {
+                let _ = (&*original).write_all(&lines);
+            });
+        }
+    }
+    if !pending.is_empty() {
+        pending.push(b'\n');
+        write_above_frame(move || {
+            let _ = (&*original).write_all(&pending);
+        });
+    }
+}
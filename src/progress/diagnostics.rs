@@ -56,6 +56,7 @@ impl JobSnapshot {
             super::ProgressStatus::Done => "done",
             super::ProgressStatus::Warn => "warn",
             super::ProgressStatus::Failed => "failed",
+            super::ProgressStatus::Cancelled => "cancelled",
         };
         drop(status);
 
@@ -67,13 +68,7 @@ impl JobSnapshot {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
-        let progress = match (
-            *job.progress_current.lock().unwrap(),
-            *job.progress_total.lock().unwrap(),
-        ) {
-            (Some(cur), Some(total)) => Some((cur, total)),
-            _ => None,
-        };
+        let progress = job.progress_current.load().zip(job.progress_total.load());
 
         let children = job
             .children
@@ -98,6 +93,8 @@ impl JobSnapshot {
 pub struct FrameEvent {
     pub rendered: String,
     pub jobs: Vec<JobSnapshot>,
+    /// Errors reported to the error handler so far.
+    pub errors: usize,
 }
 
 /// Log a frame event to the trace log file.
@@ -115,11 +112,12 @@ pub fn log_frame(rendered: &str, jobs: &[Arc<ProgressJob>]) {
     let event = FrameEvent {
         rendered,
         jobs: jobs.iter().map(|j| JobSnapshot::from_job(j)).collect(),
+        errors: super::error_count(),
     };
 
-    if let Ok(json) = serde_json::to_string(&event) {
-        if let Ok(mut writer) = log_writer.lock() {
-            let _ = writeln!(writer, "{}", json);
-        }
+    if let Ok(json) = serde_json::to_string(&event)
+        && let Ok(mut writer) = log_writer.lock()
+    {
+        let _ = writeln!(writer, "{}", json);
     }
 }
@@ -0,0 +1,65 @@
+//! Reporting of errors that have no caller to return to.
+//!
+//! Rendering and terminal writes mostly happen on the background refresh
+//! thread or inside methods that return nothing, such as `ProgressJob::update`.
+//! Their errors are passed to a process-wide handler instead.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::Error;
+
+type ErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+static ERROR_HANDLER: RwLock<Option<ErrorHandler>> = RwLock::new(None);
+static ERROR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the handler for errors raised while rendering or writing progress.
+///
+/// This covers errors from the background refresh thread, [`flush`](super::flush),
+/// job updates, and text mode output. By default they are logged with
+/// `log::error!` when the `log` feature is enabled, and printed to stderr
+/// otherwise. The handler may be called from the background thread and must
+/// not block on the progress display.
+///
+/// Errors passed to a job's
+/// [`on_render_error`](super::ProgressJobBuilder::on_render_error) callback do
+/// not reach this handler.
+///
+/// ```rust,no_run
+/// clx::progress::set_error_handler(|err| {
+///     // Route into the application's own diagnostics instead of stderr.
+///     let _ = err;
+/// });
+/// ```
+pub fn set_error_handler<F>(handler: F)
+where
+    F: Fn(&Error) + Send + Sync + 'static,
+{
+    *ERROR_HANDLER.write().unwrap() = Some(Arc::new(handler));
+}
+
+/// Returns how many errors have been passed to the error handler.
+pub fn error_count() -> usize {
+    ERROR_COUNT.load(Ordering::Relaxed)
+}
+
+/// Counts an error and passes it to the error handler.
+pub(crate) fn report_error(err: &Error) {
+    ERROR_COUNT.fetch_add(1, Ordering::Relaxed);
+    let handler = ERROR_HANDLER.read().unwrap().clone();
+    match handler {
+        Some(handler) => handler(err),
+        None => default_handler(err),
+    }
+}
+
+#[cfg(feature = "log")]
+fn default_handler(err: &Error) {
+    log::error!(target: "clx", "{err}");
+}
+
+#[cfg(not(feature = "log"))]
+fn default_handler(err: &Error) {
+    eprintln!("clx: {err:?}");
+}
@@ -0,0 +1,313 @@
+//! Job events, delivered to per-job callbacks and to subscribed channels.
+//!
+//! Status changes and progress updates are reported from the methods that
+//! make them, such as [`set_status`](super::ProgressJob::set_status) and
+//! [`increment`](super::ProgressJob::increment), so notifications and
+//! metrics can be driven off the progress objects themselves.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use super::job::ProgressStatus;
+
+type StatusCallback = Arc<dyn Fn(&ProgressStatus, &ProgressStatus) + Send + Sync>;
+type ProgressCallback = Arc<dyn Fn(usize, Option<usize>) + Send + Sync>;
+type DoneCallback = Arc<dyn Fn(&ProgressStatus) + Send + Sync>;
+
+static SUBSCRIBERS: Mutex<Vec<Sender<ProgressEvent>>> = Mutex::new(Vec::new());
+
+/// Length of `SUBSCRIBERS`, so jobs can skip the lock when no one listens.
+static SUBSCRIBER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Minimum time between two [`ProgressEvent::Progress`] events of one job.
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reference point for the per-job progress event deadlines.
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// A change to a job, sent to receivers returned by [`subscribe`].
+///
+/// Jobs are identified by [`ProgressJob::id`](super::ProgressJob::id).
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// The job's status changed from `old` to `new`.
+    StatusChanged {
+        job: usize,
+        old: ProgressStatus,
+        new: ProgressStatus,
+        at: Instant,
+    },
+    /// The job's progress was updated.
+    Progress {
+        job: usize,
+        current: usize,
+        total: Option<usize>,
+    },
+}
+
+/// Returns a channel that receives an event for every status change and
+/// progress update of every job.
+///
+/// Events are sent from the thread that made the change, in order. Progress
+/// updates are coalesced: each job sends at most one
+/// [`Progress`](ProgressEvent::Progress) event every 50ms, carrying its latest
+/// values, and one held back is sent before the job's next status change.
+/// The channel is unbounded, so a receiver should be drained or dropped; once
+/// it is dropped, its sender is removed on the next event.
+///
+/// ```rust,no_run
+/// use clx::progress::{ProgressEvent, ProgressJobBuilder, ProgressStatus, subscribe};
+///
+/// let events = subscribe();
+/// std::thread::spawn(move || {
+///     for event in events {
+///         if let ProgressEvent::StatusChanged { job, new, .. } = event {
+///             eprintln!("job {job} is now {new:?}");
+///         }
+///     }
+/// });
+/// let job = ProgressJobBuilder::new().prop("message", "Working").start();
+/// job.set_status(ProgressStatus::Done);
+/// ```
+pub fn subscribe() -> Receiver<ProgressEvent> {
+    let (sender, receiver) = channel();
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.push(sender);
+    SUBSCRIBER_COUNT.store(subscribers.len(), Ordering::Release);
+    receiver
+}
+
+fn has_subscribers() -> bool {
+    SUBSCRIBER_COUNT.load(Ordering::Acquire) > 0
+}
+
+fn emit(event: ProgressEvent) {
+    if !has_subscribers() {
+        return;
+    }
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+    SUBSCRIBER_COUNT.store(subscribers.len(), Ordering::Release);
+}
+
+/// Callbacks registered on one job, and the job's status history.
+#[derive(Default)]
+pub(crate) struct JobEvents {
+    status: Mutex<Vec<StatusCallback>>,
+    progress: Mutex<Vec<ProgressCallback>>,
+    /// Whether `progress` is non-empty, so updates can skip its lock.
+    has_progress_callbacks: AtomicBool,
+    done: Mutex<Vec<DoneCallback>>,
+    history: Mutex<Vec<(ProgressStatus, Instant)>>,
+    /// Milliseconds after `EPOCH` before which no progress event is sent.
+    progress_event_due: AtomicU64,
+    /// Whether a progress update was not sent because it came too soon.
+    progress_event_held: AtomicBool,
+}
+
+impl JobEvents {
+    pub(crate) fn new(status: &ProgressStatus) -> Self {
+        Self {
+            history: Mutex::new(vec![(status.clone(), Instant::now())]),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn on_status_change(&self, callback: StatusCallback) {
+        self.status.lock().unwrap().push(callback);
+    }
+
+    pub(crate) fn on_progress(&self, callback: ProgressCallback) {
+        self.progress.lock().unwrap().push(callback);
+        self.has_progress_callbacks.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn on_done(&self, callback: DoneCallback) {
+        self.done.lock().unwrap().push(callback);
+    }
+
+    pub(crate) fn history(&self) -> Vec<(ProgressStatus, Instant)> {
+        self.history.lock().unwrap().clone()
+    }
+
+    /// Records a status change and reports it, after any progress event held
+    /// back. Callbacks run without any of the job's locks held, so they may
+    /// update the job. `progress` reads the job's current and total progress.
+    pub(crate) fn status_changed(
+        &self,
+        job: usize,
+        old: &ProgressStatus,
+        new: &ProgressStatus,
+        progress: impl FnOnce() -> (usize, Option<usize>),
+    ) {
+        if self.progress_event_held.swap(false, Ordering::AcqRel) {
+            emit_progress(job, progress());
+        }
+        let at = Instant::now();
+        self.history.lock().unwrap().push((new.clone(), at));
+        emit(ProgressEvent::StatusChanged {
+            job,
+            old: old.clone(),
+            new: new.clone(),
+            at,
+        });
+        let callbacks = self.status.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(old, new);
+        }
+        if new.is_finished() && !old.is_finished() {
+            let callbacks = self.done.lock().unwrap().clone();
+            for callback in callbacks {
+                callback(new);
+            }
+        }
+    }
+
+    /// Reports a progress update to the job's callbacks and, at most once per
+    /// [`PROGRESS_EVENT_INTERVAL`], to subscribers. `progress` reads the job's
+    /// latest values for the event, which may include later updates.
+    pub(crate) fn progress_changed(
+        &self,
+        job: usize,
+        current: usize,
+        total: Option<usize>,
+        progress: impl FnOnce() -> (usize, Option<usize>),
+    ) {
+        if has_subscribers() && self.progress_event_due() {
+            // Cleared before reading, so an update racing this one either is
+            // read below or leaves the flag set for the next event.
+            self.progress_event_held.store(false, Ordering::Release);
+            emit_progress(job, progress());
+        }
+        if self.has_progress_callbacks.load(Ordering::Acquire) {
+            let callbacks = self.progress.lock().unwrap().clone();
+            for callback in callbacks {
+                callback(current, total);
+            }
+        }
+    }
+
+    /// Claims the next progress event slot, or records that an update was
+    /// held back if the last event was sent too recently.
+    fn progress_event_due(&self) -> bool {
+        let now = EPOCH.elapsed().as_millis() as u64;
+        let due = self.progress_event_due.load(Ordering::Acquire);
+        let next = now + PROGRESS_EVENT_INTERVAL.as_millis() as u64;
+        if now >= due
+            && self
+                .progress_event_due
+                .compare_exchange(due, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            return true;
+        }
+        self.progress_event_held.store(true, Ordering::Release);
+        false
+    }
+}
+
+fn emit_progress(job: usize, (current, total): (usize, Option<usize>)) {
+    emit(ProgressEvent::Progress {
+        job,
+        current,
+        total,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressJobBuilder;
+
+    #[test]
+    fn test_status_callbacks_and_history() {
+        let job = ProgressJobBuilder::new().build();
+        let changes = Arc::new(Mutex::new(vec![]));
+        let done = Arc::new(Mutex::new(vec![]));
+        let c = changes.clone();
+        job.on_status_change(move |old, new| c.lock().unwrap().push((old.clone(), new.clone())));
+        let d = done.clone();
+        job.on_done(move |status| d.lock().unwrap().push(status.clone()));
+
+        job.set_status(ProgressStatus::Done);
+        job.set_status(ProgressStatus::Warn);
+
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [
+                (ProgressStatus::Running, ProgressStatus::Done),
+                (ProgressStatus::Done, ProgressStatus::Warn),
+            ]
+        );
+        assert_eq!(*done.lock().unwrap(), [ProgressStatus::Done]);
+        let history = job
+            .status_history()
+            .into_iter()
+            .map(|(status, _)| status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            [
+                ProgressStatus::Running,
+                ProgressStatus::Done,
+                ProgressStatus::Warn
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subscribe_receives_job_events() {
+        let events = subscribe();
+        let job = ProgressJobBuilder::new().build();
+        let progress = Arc::new(Mutex::new(vec![]));
+        let p = progress.clone();
+        job.on_progress(move |current, total| p.lock().unwrap().push((current, total)));
+
+        job.progress_total(10);
+        job.increment(4);
+        job.cancel();
+
+        assert_eq!(*progress.lock().unwrap(), [(0, Some(10)), (4, Some(10))]);
+        // Other tests' jobs report to the same channel.
+        let events = events
+            .try_iter()
+            .filter(|event| match event {
+                ProgressEvent::StatusChanged { job: id, .. }
+                | ProgressEvent::Progress { job: id, .. } => *id == job.id(),
+            })
+            .map(|event| match event {
+                ProgressEvent::StatusChanged { new, .. } => format!("{new:?}"),
+                ProgressEvent::Progress { current, .. } => current.to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(events, ["0", "4", "Cancelled"]);
+    }
+
+    #[test]
+    fn test_progress_events_are_coalesced() {
+        let events = subscribe();
+        let job = ProgressJobBuilder::new().build();
+
+        job.progress_total(10_000);
+        for _ in 0..10_000 {
+            job.increment(1);
+        }
+        job.set_status(ProgressStatus::Done);
+
+        let progress = events
+            .try_iter()
+            .filter_map(|event| match event {
+                ProgressEvent::Progress {
+                    job: id, current, ..
+                } if id == job.id() => Some(current),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(progress.len() < 100, "{} progress events", progress.len());
+        // The last update is sent before the job finishes.
+        assert_eq!(progress.last(), Some(&10_000));
+    }
+}
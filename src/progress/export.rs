@@ -0,0 +1,296 @@
+//! Export of job timings for profiling tools.
+//!
+//! The job tree is a span tree: every job has a start, an end once it reaches
+//! a final status, and children. These functions write it in formats that
+//! profilers read, so a multi-step run can be inspected after the fact.
+//! Jobs that have left the tree are included if they finished while
+//! [`set_job_history`](super::set_job_history) was recording. Jobs that are
+//! still running end at the time of the export.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::Result;
+
+use super::history::{self, JobRecord};
+
+/// Writes every job as Chrome trace-event JSON.
+///
+/// Open the file in [Perfetto](https://ui.perfetto.dev) or `chrome://tracing`.
+/// Each job is a complete event named after its `message`, with its status in
+/// `args`. Times are in microseconds from the earliest job's start. A child
+/// job shares its parent's track unless it overlaps an earlier sibling, in
+/// which case it gets a track of its own.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// ```rust,no_run
+/// use clx::progress::write_chrome_trace;
+///
+/// let file = std::fs::File::create("build-trace.json").unwrap();
+/// write_chrome_trace(file).unwrap();
+/// ```
+pub fn write_chrome_trace(writer: impl Write) -> Result<()> {
+    let trace = chrome_trace(&history::records(), Instant::now());
+    serde_json::to_writer(writer, &trace).map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+/// Writes every job in folded-stack format.
+///
+/// Each line is a job's path from its top-level job, separated by `;`, and
+/// the job's self time in microseconds: its duration minus that of its
+/// children. This is the input format of `flamegraph.pl`, `inferno`, and
+/// speedscope.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// ```rust,no_run
+/// use clx::progress::write_folded_stacks;
+///
+/// let file = std::fs::File::create("build.folded").unwrap();
+/// write_folded_stacks(file).unwrap();
+/// ```
+pub fn write_folded_stacks(mut writer: impl Write) -> Result<()> {
+    let jobs = history::records();
+    let tree = Tree::new(&jobs);
+    let now = Instant::now();
+    let mut lines = vec![];
+    for job in tree.children(None) {
+        folded_stacks(&tree, job, "", now, &mut lines);
+    }
+    for line in lines {
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
+}
+
+/// The records of a job tree, grouped by parent.
+struct Tree<'a> {
+    children: HashMap<Option<usize>, Vec<&'a JobRecord>>,
+}
+
+impl<'a> Tree<'a> {
+    /// Groups `jobs` by parent, each group ordered by start. A job whose
+    /// parent is not in `jobs` is a root.
+    fn new(jobs: &'a [JobRecord]) -> Self {
+        let ids = jobs.iter().map(|job| job.id).collect::<HashSet<_>>();
+        let mut children = HashMap::<_, Vec<_>>::new();
+        for job in jobs {
+            let parent = job.parent.filter(|parent| ids.contains(parent));
+            children.entry(parent).or_default().push(job);
+        }
+        for group in children.values_mut() {
+            group.sort_by_key(|job| job.start);
+        }
+        Self { children }
+    }
+
+    /// The children of the job with id `parent`, or the roots for `None`.
+    fn children(&self, parent: Option<usize>) -> &[&'a JobRecord] {
+        self.children.get(&parent).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// When `job` ended, or `now` if it has not.
+fn end_time(job: &JobRecord, now: Instant) -> Instant {
+    job.end.unwrap_or(now).max(job.start)
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+fn chrome_trace(jobs: &[JobRecord], now: Instant) -> Value {
+    let tree = Tree::new(jobs);
+    let mut trace = ChromeTrace {
+        origin: jobs.iter().map(|job| job.start).min().unwrap_or(now),
+        now,
+        events: vec![],
+        tracks: 0,
+    };
+    for job in tree.children(None) {
+        let tid = trace.new_track(&job.label);
+        trace.add(&tree, job, tid);
+    }
+    json!({
+        "traceEvents": trace.events,
+        "displayTimeUnit": "ms",
+    })
+}
+
+struct ChromeTrace {
+    origin: Instant,
+    now: Instant,
+    events: Vec<Value>,
+    tracks: usize,
+}
+
+impl ChromeTrace {
+    /// Allocates a track and names it after the job that opens it.
+    fn new_track(&mut self, name: &str) -> usize {
+        self.tracks += 1;
+        self.events.push(json!({
+            "ph": "M",
+            "name": "thread_name",
+            "pid": 1,
+            "tid": self.tracks,
+            "args": { "name": name },
+        }));
+        self.tracks
+    }
+
+    fn add(&mut self, tree: &Tree<'_>, job: &JobRecord, tid: usize) {
+        let end = end_time(job, self.now);
+        self.events.push(json!({
+            "ph": "X",
+            "name": job.label,
+            "cat": "job",
+            "pid": 1,
+            "tid": tid,
+            "ts": micros(job.start.saturating_duration_since(self.origin)),
+            "dur": micros(end - job.start),
+            "args": { "status": format!("{:?}", job.status) },
+        }));
+
+        // End of the last child placed on the parent's track.
+        let mut track_end = job.start;
+        for &child in tree.children(Some(job.id)) {
+            let child_tid = if child.start >= track_end {
+                track_end = end_time(child, self.now);
+                tid
+            } else {
+                self.new_track(&child.label)
+            };
+            self.add(tree, child, child_tid);
+        }
+    }
+}
+
+fn folded_stacks(
+    tree: &Tree<'_>,
+    job: &JobRecord,
+    prefix: &str,
+    now: Instant,
+    lines: &mut Vec<String>,
+) {
+    // `;` separates frames and a newline ends the record.
+    let name = job.label.replace(';', ",").replace('\n', " ");
+    let path = if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix};{name}")
+    };
+    let children = tree.children(Some(job.id));
+    let children_time = children
+        .iter()
+        .map(|child| end_time(child, now) - child.start)
+        .sum::<Duration>();
+    let self_time = (end_time(job, now) - job.start).saturating_sub(children_time);
+    lines.push(format!("{path} {}", micros(self_time)));
+    for child in children {
+        folded_stacks(tree, child, &path, now, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::progress::ProgressJobBuilder;
+    use crate::progress::job::ProgressJob;
+
+    /// Builds a job that ran from `start` to `end` milliseconds after `origin`.
+    fn job(name: &str, origin: Instant, start: u64, end: u64) -> ProgressJob {
+        let mut job = ProgressJobBuilder::new().prop("message", name).build();
+        job.start = origin + Duration::from_millis(start);
+        *job.finished.lock().unwrap() = Some(origin + Duration::from_millis(end));
+        job
+    }
+
+    fn records(root: Arc<ProgressJob>) -> Vec<JobRecord> {
+        history::merge(vec![], &[root])
+    }
+
+    fn tree(origin: Instant) -> Arc<ProgressJob> {
+        let build = Arc::new(job("build", origin, 0, 100));
+        let compile = build.add(job("compile", origin, 0, 60));
+        compile.add(job("codegen", origin, 10, 50));
+        // Overlaps `compile`, so it moves to its own track.
+        build.add(job("docs", origin, 20, 40));
+        build.add(job("link;strip", origin, 60, 90));
+        build
+    }
+
+    #[test]
+    fn test_chrome_trace_events() {
+        let origin = Instant::now();
+        let trace = chrome_trace(&records(tree(origin)), origin);
+        let events = trace["traceEvents"].as_array().unwrap();
+        let slices = events
+            .iter()
+            .filter(|e| e["ph"] == "X")
+            .map(|e| {
+                (
+                    e["name"].as_str().unwrap(),
+                    e["tid"].as_u64().unwrap(),
+                    e["ts"].as_u64().unwrap(),
+                    e["dur"].as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            slices,
+            [
+                ("build", 1, 0, 100_000),
+                ("compile", 1, 0, 60_000),
+                ("codegen", 1, 10_000, 40_000),
+                ("docs", 2, 20_000, 20_000),
+                ("link;strip", 1, 60_000, 30_000),
+            ]
+        );
+        let tracks = events
+            .iter()
+            .filter(|e| e["ph"] == "M")
+            .map(|e| e["args"]["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(tracks, ["build", "docs"]);
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let origin = Instant::now();
+        let jobs = records(tree(origin));
+        let tree = Tree::new(&jobs);
+        let mut lines = vec![];
+        folded_stacks(&tree, tree.children(None)[0], "", origin, &mut lines);
+        assert_eq!(
+            lines,
+            [
+                "build 0",
+                "build;compile 20000",
+                "build;compile;codegen 40000",
+                "build;docs 20000",
+                "build;link,strip 30000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_running_job_ends_at_export() {
+        let origin = Instant::now();
+        let mut running = ProgressJobBuilder::new().prop("message", "run").build();
+        running.start = origin;
+        let now = origin + Duration::from_millis(5);
+        let trace = chrome_trace(&records(Arc::new(running)), now);
+        assert_eq!(trace["traceEvents"][1]["dur"], 5_000);
+    }
+}
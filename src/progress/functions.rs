@@ -0,0 +1,353 @@
+//! Template functions and filters shared by all template engines.
+//!
+//! Each engine parses its own arguments and calls into these so that
+//! `{{ spinner() }}` renders identically whichever engine a job uses.
+
+use std::time::Duration;
+
+use crate::{Error, Result, progress_bar, style};
+
+use super::flex::{encode_progress_bar_chars, safe_prefix};
+use super::format::{format_bytes, format_count, format_duration};
+use super::job::{ProgressJob, ProgressStatus};
+use super::output::{ProgressOutput, output};
+use super::render::RenderContext;
+use super::spinners::{DEFAULT_SPINNER, SPINNERS, Spinner};
+
+/// Per-job values the template functions read while rendering.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct RenderState {
+    /// Time since the render context started, used to pick spinner frames.
+    pub frame_elapsed_ms: usize,
+    /// Job duration, frozen once the job finishes.
+    pub job_elapsed_secs: f64,
+    pub job_finished: bool,
+    /// Elapsed time of the current operation, for ETA/rate after next_operation().
+    pub operation_elapsed_secs: f64,
+    /// Fixed spinner() output, or `None` while the spinner animates.
+    pub status_icon: Option<String>,
+    pub progress: Option<(usize, usize)>,
+    pub smoothed_rate: Option<f64>,
+    pub width: usize,
+}
+
+impl RenderState {
+    pub fn new(ctx: &RenderContext, job: &ProgressJob) -> Self {
+        Self {
+            frame_elapsed_ms: ctx.elapsed().as_millis() as usize,
+            job_elapsed_secs: job.duration().as_secs_f64(),
+            job_finished: job.finished_at().is_some(),
+            operation_elapsed_secs: job.operation_start.lock().unwrap().elapsed().as_secs_f64(),
+            status_icon: status_icon(&job.status.lock().unwrap()),
+            progress: ctx.progress,
+            smoothed_rate: job.smoothed_rate.load(),
+            width: ctx.width,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.progress
+            .map(|(cur, total)| cur >= total)
+            .unwrap_or(false)
+    }
+}
+
+/// Returns the fixed spinner() output for a status, or `None` while it animates.
+pub(crate) fn status_icon(status: &ProgressStatus) -> Option<String> {
+    match status {
+        ProgressStatus::Running if output() == ProgressOutput::Text => Some(" ".to_string()),
+        ProgressStatus::Running => None,
+        ProgressStatus::Hide => Some(" ".to_string()),
+        ProgressStatus::Pending => Some(style::eyellow("⏸").dim().to_string()),
+        ProgressStatus::Done => Some(style::egreen("✔").bright().to_string()),
+        ProgressStatus::Failed => Some(style::ered("✗").to_string()),
+        ProgressStatus::RunningCustom(s) => Some(s.clone()),
+        ProgressStatus::DoneCustom(s) => Some(s.clone()),
+        ProgressStatus::Warn => Some(style::eyellow("⚠").to_string()),
+        ProgressStatus::Cancelled => Some(style::edim("⊘").to_string()),
+    }
+}
+
+/// elapsed() - time since job started, or until it finished.
+pub(crate) fn elapsed(state: &RenderState) -> String {
+    format_duration(Duration::from_secs_f64(state.job_elapsed_secs))
+}
+
+/// duration() - time the job took, empty until it finishes.
+pub(crate) fn duration(state: &RenderState) -> String {
+    if state.job_finished {
+        elapsed(state)
+    } else {
+        String::new()
+    }
+}
+
+/// eta() - estimated time remaining (uses operation-specific elapsed time for fallback).
+pub(crate) fn eta(state: &RenderState, hide_complete: bool) -> String {
+    let (eta_value, eta_is_complete) = calculate_eta(
+        state.progress,
+        state.smoothed_rate,
+        state.operation_elapsed_secs,
+    );
+    if hide_complete && (eta_is_complete || eta_value.is_none()) {
+        "".to_string()
+    } else {
+        eta_value.unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// Calculate ETA based on progress and rate.
+/// Uses operation_elapsed_secs for linear extrapolation fallback to give accurate
+/// estimates after next_operation() resets the smoothed rate.
+fn calculate_eta(
+    progress: Option<(usize, usize)>,
+    smoothed_rate: Option<f64>,
+    operation_elapsed_secs: f64,
+) -> (Option<String>, bool) {
+    if let Some((cur, total)) = progress {
+        if cur > 0 && total > 0 && cur <= total {
+            let remaining_items = (total - cur) as f64;
+
+            let remaining_secs = if let Some(rate) = smoothed_rate {
+                if rate > 0.0 {
+                    remaining_items / rate
+                } else {
+                    // Fall back to linear extrapolation using operation-specific elapsed time
+                    let progress_ratio = cur as f64 / total as f64;
+                    let estimated_total = operation_elapsed_secs / progress_ratio;
+                    estimated_total - operation_elapsed_secs
+                }
+            } else {
+                // No smoothed rate yet, use linear extrapolation with operation-specific time
+                let progress_ratio = cur as f64 / total as f64;
+                let estimated_total = operation_elapsed_secs / progress_ratio;
+                estimated_total - operation_elapsed_secs
+            };
+
+            if remaining_secs > 0.0 {
+                (
+                    Some(format_duration(Duration::from_secs_f64(remaining_secs))),
+                    false,
+                )
+            } else {
+                (Some("0s".to_string()), true)
+            }
+        } else {
+            (None, cur >= total)
+        }
+    } else {
+        (None, false)
+    }
+}
+
+/// rate() - throughput of the current operation.
+/// Uses operation_elapsed_secs for average rate fallback to give accurate
+/// rates after next_operation() resets the smoothed rate.
+pub(crate) fn rate(state: &RenderState) -> String {
+    if let Some((cur, _total)) = state.progress {
+        let rate = state.smoothed_rate.unwrap_or_else(|| {
+            if state.operation_elapsed_secs > 0.0 && cur > 0 {
+                cur as f64 / state.operation_elapsed_secs
+            } else {
+                0.0
+            }
+        });
+        if rate >= 1.0 {
+            format!("{:.1}/s", rate)
+        } else if rate >= 1.0 / 60.0 {
+            format!("{:.1}/m", rate * 60.0)
+        } else if rate > 0.0 {
+            format!("{:.2}/s", rate)
+        } else {
+            "-/s".to_string()
+        }
+    } else {
+        "-/s".to_string()
+    }
+}
+
+/// bytes() - show progress as human-readable bytes.
+///
+/// `hide_complete` returns an empty string when progress is 100%; with
+/// `show_total` false only the current bytes are shown, without "/ total".
+pub(crate) fn bytes(state: &RenderState, hide_complete: bool, show_total: bool) -> String {
+    if hide_complete && state.is_complete() {
+        return "".to_string();
+    }
+    match state.progress {
+        Some((cur, total)) if show_total => {
+            format!("{} / {}", format_bytes(cur), format_bytes(total))
+        }
+        Some((cur, _)) => format_bytes(cur),
+        None => "".to_string(),
+    }
+}
+
+/// percentage() - show progress as percentage.
+pub(crate) fn percentage(state: &RenderState, hide_complete: bool, decimals: i64) -> String {
+    if hide_complete && state.is_complete() {
+        return "".to_string();
+    }
+    match state.progress {
+        Some((cur, total)) if total > 0 => {
+            let pct = (cur as f64 / total as f64) * 100.0;
+            let decimals = decimals.clamp(0, 20) as usize;
+            format!("{:.prec$}%", pct, prec = decimals)
+        }
+        Some(_) => "0%".to_string(),
+        None => "".to_string(),
+    }
+}
+
+/// count_format() - show a number (default: current progress) in human-readable format.
+pub(crate) fn count_format(state: &RenderState, value: Option<i64>, decimals: i64) -> String {
+    let value = value
+        .map(|v| v.max(0) as usize)
+        .or_else(|| state.progress.map(|(cur, _)| cur));
+    match value {
+        Some(n) => format_count(n, decimals.clamp(0, 20) as usize),
+        None => "".to_string(),
+    }
+}
+
+/// spinner() - the status icon, or an animated spinner while running.
+pub(crate) fn spinner(state: &RenderState, name: Option<&str>) -> Result<String> {
+    let spinner = find_spinner(name.unwrap_or(DEFAULT_SPINNER))?;
+    if let Some(icon) = &state.status_icon {
+        return Ok(icon.clone());
+    }
+    let frame_index = (state.frame_elapsed_ms / spinner.fps) % spinner.frames.len();
+    let frame = spinner.frames[frame_index].clone();
+    Ok(style::eblue(frame).to_string())
+}
+
+/// Looks up a spinner by the name given to spinner(name='...').
+pub(crate) fn find_spinner(name: &str) -> Result<&'static Spinner> {
+    SPINNERS
+        .get(name)
+        .ok_or_else(|| Error::Template(format!("unknown spinner `{name}`")))
+}
+
+/// Options accepted by progress_bar().
+#[derive(Debug, Default)]
+pub(crate) struct ProgressBarArgs<'a> {
+    pub hide_complete: bool,
+    pub flex: bool,
+    /// Fixed width; negative values are subtracted from the terminal width.
+    pub width: Option<i64>,
+    pub style: Option<&'a str>,
+    pub fill: Option<&'a str>,
+    pub head: Option<&'a str>,
+    pub empty: Option<&'a str>,
+    pub left: Option<&'a str>,
+    pub right: Option<&'a str>,
+}
+
+impl ProgressBarArgs<'_> {
+    /// Build progress bar characters from the preset style or individual options.
+    fn chars(&self) -> progress_bar::ProgressBarChars {
+        // Check for preset style first
+        match self.style {
+            Some("blocks") => return progress_bar::ProgressBarChars::blocks(),
+            Some("thin") => return progress_bar::ProgressBarChars::thin(),
+            _ => {}
+        }
+
+        // Build from individual character options
+        let mut chars = progress_bar::ProgressBarChars::default();
+        if let Some(fill) = self.fill {
+            chars.fill = fill.to_string();
+        }
+        if let Some(head) = self.head {
+            chars.head = head.to_string();
+        }
+        if let Some(empty) = self.empty {
+            chars.empty = empty.to_string();
+        }
+        if let Some(left) = self.left {
+            chars.left = left.to_string();
+        }
+        if let Some(right) = self.right {
+            chars.right = right.to_string();
+        }
+        chars
+    }
+}
+
+/// progress_bar() - a fixed-width bar, or a flex placeholder sized at layout time.
+pub(crate) fn progress_bar(state: &RenderState, args: &ProgressBarArgs<'_>) -> String {
+    let Some((progress_current, progress_total)) = state.progress else {
+        return "".to_string();
+    };
+    if args.hide_complete && progress_current >= progress_total {
+        return "".to_string();
+    }
+
+    let chars = args.chars();
+    if args.flex {
+        let chars_encoded = encode_progress_bar_chars(&chars);
+        format!(
+            "<clx:flex><clx:progress cur={} total={} chars={}><clx:flex>",
+            progress_current, progress_total, chars_encoded
+        )
+    } else {
+        let width = state.width;
+        let bar_width = args
+            .width
+            .map(|v| {
+                if v < 0 {
+                    width - (-v as usize)
+                } else {
+                    v as usize
+                }
+            })
+            .unwrap_or(width);
+        progress_bar::progress_bar_with_chars(progress_current, progress_total, bar_width, &chars)
+    }
+}
+
+/// flex filter - truncates content to fit.
+pub(crate) fn flex(content: &str) -> String {
+    format!("<clx:flex>{}<clx:flex>", content)
+}
+
+/// flex_fill filter - pads content to fill available width.
+pub(crate) fn flex_fill(content: &str) -> String {
+    format!("<clx:flex_fill>{}<clx:flex_fill>", content)
+}
+
+/// truncate_text filter - simple truncation for text mode.
+pub(crate) fn truncate_text(
+    state: &RenderState,
+    content: String,
+    prefix_len: Option<i64>,
+    length: Option<i64>,
+) -> String {
+    let prefix_len = prefix_len.map(|v| v as usize).unwrap_or(20);
+    let max_len = length
+        .map(|v| v as usize)
+        .unwrap_or_else(|| state.width.saturating_sub(prefix_len));
+
+    if content.len() <= max_len {
+        content
+    } else if max_len > 1 {
+        format!("{}…", safe_prefix(&content, max_len.saturating_sub(1)))
+    } else {
+        "…".to_string()
+    }
+}
+
+type StyleFn = fn(&str) -> String;
+
+/// Color and style filters, by name.
+pub(crate) const STYLE_FILTERS: &[(&str, StyleFn)] = &[
+    ("cyan", |s| style::ecyan(s).to_string()),
+    ("blue", |s| style::eblue(s).to_string()),
+    ("green", |s| style::egreen(s).to_string()),
+    ("yellow", |s| style::eyellow(s).to_string()),
+    ("red", |s| style::ered(s).to_string()),
+    ("magenta", |s| style::emagenta(s).to_string()),
+    ("bold", |s| style::ebold(s).to_string()),
+    ("dim", |s| style::edim(s).to_string()),
+    ("underline", |s| style::eunderline(s).to_string()),
+];
@@ -0,0 +1,140 @@
+//! A record of finished jobs, for reports made after the fact.
+//!
+//! Jobs can leave the tree before the display stops, for example when they are
+//! removed or when a [`Print`](super::ProgressJobDoneBehavior::Print) job
+//! finishes. The summary and the timing exports read this record, so those
+//! jobs are still reported. Jobs are only recorded while a summary is set or
+//! [`set_job_history`] has turned recording on.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Instant;
+
+use super::job::{ProgressJob, ProgressStatus};
+use super::state::JOBS;
+
+static FINISHED: LazyLock<Mutex<HashMap<usize, JobRecord>>> = LazyLock::new(Default::default);
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// A job as reports see it.
+#[derive(Debug, Clone)]
+pub(crate) struct JobRecord {
+    pub(crate) id: usize,
+    pub(crate) parent: Option<usize>,
+    pub(crate) label: String,
+    pub(crate) status: ProgressStatus,
+    pub(crate) start: Instant,
+    pub(crate) end: Option<Instant>,
+}
+
+impl JobRecord {
+    fn new(job: &ProgressJob) -> Self {
+        Self {
+            id: job.id(),
+            parent: job.parent.upgrade().map(|parent| parent.id()),
+            label: job.label(),
+            status: job.status.lock().unwrap().clone(),
+            start: job.start,
+            end: job.finished_at(),
+        }
+    }
+}
+
+/// Keeps a record of every job that finishes, so
+/// [`write_chrome_trace`](super::write_chrome_trace) and
+/// [`write_folded_stacks`](super::write_folded_stacks) include jobs that have
+/// left the tree, such as removed jobs and
+/// [`Print`](super::ProgressJobDoneBehavior::Print) jobs.
+///
+/// Recording is off by default, since the record grows with every job until
+/// [`clear_jobs`](super::clear_jobs) drops it. A summary set with
+/// [`set_summary`](super::set_summary) records jobs on its own until
+/// [`stop`](super::stop) prints it.
+pub fn set_job_history(enabled: bool) {
+    RECORDING.store(enabled, Ordering::Relaxed);
+}
+
+fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed) || super::summary::is_set()
+}
+
+/// Records `job` with its current status, replacing an earlier record of it.
+/// Called when a job reaches a final status.
+pub(crate) fn record_finished(job: &ProgressJob) {
+    if is_recording() {
+        let record = JobRecord::new(job);
+        FINISHED.lock().unwrap().insert(record.id, record);
+    }
+}
+
+/// Forgets a job that has left its final status to run again.
+pub(crate) fn forget(id: usize) {
+    FINISHED.lock().unwrap().remove(&id);
+}
+
+/// Forgets the jobs recorded only for the summary. Called by `stop` once the
+/// summary is printed.
+pub(crate) fn release() {
+    if !RECORDING.load(Ordering::Relaxed) {
+        clear();
+    }
+}
+
+/// Forgets every finished job. Called by `clear_jobs`.
+pub(crate) fn clear() {
+    FINISHED.lock().unwrap().clear();
+}
+
+/// Returns every finished job, and every other job in the tree, in the order
+/// they started.
+pub(crate) fn records() -> Vec<JobRecord> {
+    let mut finished = FINISHED
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    finished.sort_by_key(|r| (r.start, r.id));
+    let jobs = JOBS.lock().unwrap().clone();
+    merge(finished, &jobs)
+}
+
+/// Adds the jobs of the tree under `jobs` that are not already in `records`.
+pub(crate) fn merge(mut records: Vec<JobRecord>, jobs: &[Arc<ProgressJob>]) -> Vec<JobRecord> {
+    fn walk(jobs: &[Arc<ProgressJob>], seen: &HashSet<usize>, records: &mut Vec<JobRecord>) {
+        for job in jobs {
+            if !seen.contains(&job.id()) {
+                records.push(JobRecord::new(job));
+            }
+            walk(&job.children(), seen, records);
+        }
+    }
+    let seen = records.iter().map(|r| r.id).collect::<HashSet<_>>();
+    walk(jobs, &seen, &mut records);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressJobBuilder;
+
+    #[test]
+    fn test_merge_skips_recorded_jobs() {
+        let parent = Arc::new(ProgressJobBuilder::new().prop("message", "build").build());
+        let child = parent.add(ProgressJobBuilder::new().prop("message", "lint").build());
+        let recorded = vec![JobRecord::new(&child)];
+        let labels = merge(recorded, std::slice::from_ref(&parent))
+            .into_iter()
+            .map(|r| (r.label, r.parent))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                ("lint".to_string(), Some(parent.id())),
+                ("build".to_string(), None)
+            ]
+        );
+    }
+}
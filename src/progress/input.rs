@@ -0,0 +1,411 @@
+//! Keyboard input while the progress display is running.
+//!
+//! When enabled with [`set_keyboard_input`], an input thread is started
+//! alongside the refresh thread. It switches the terminal to non-canonical,
+//! no-echo mode so single key presses can be read, and passes each key to the
+//! handler set with [`on_key`] before applying the built-in actions:
+//!
+//! | Key | Action |
+//! |-----|--------|
+//! | `v` | Toggle showing the children of top-level jobs |
+//! | `q` | Cancel every job, as [`ProgressJob::cancel`](super::ProgressJob::cancel) does |
+//! | `↑` / `↓` | Scroll a job list taller than the terminal by one line |
+//! | `PgUp` / `PgDn` | Scroll by a page |
+//! | `Home` / `End` | Scroll to the top or bottom |
+//!
+//! Signal keys such as Ctrl-C keep working. The terminal mode is restored when
+//! the display stops, by [`stop`](super::stop), and if the program panics.
+
+use std::io::{self, IsTerminal};
+use std::os::fd::AsFd;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::termios::{LocalFlags, SetArg, Termios, tcgetattr, tcsetattr};
+
+use super::output::{ProgressOutput, output};
+use super::state::{JOBS, STARTED, is_paused, is_prompting, notify};
+use crate::style;
+
+/// How long the input thread waits for a key before checking whether the
+/// display has stopped.
+const POLL_TIMEOUT_MS: u16 = 50;
+
+/// A key pressed while the progress display is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Key {
+    /// A printable character.
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+}
+
+type KeyHandler = Arc<dyn Fn(Key) -> bool + Send + Sync>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static KEY_HANDLER: RwLock<Option<KeyHandler>> = RwLock::new(None);
+/// Whether `v` has hidden the children of top-level jobs.
+static COLLAPSED: AtomicBool = AtomicBool::new(false);
+/// First line of the frame shown when it is taller than the terminal.
+static SCROLL: AtomicUsize = AtomicUsize::new(0);
+static INPUT: Mutex<Option<InputThread>> = Mutex::new(None);
+/// Held by the input thread from its pause check until its read returns.
+static READING: Mutex<()> = Mutex::new(());
+
+/// The running input thread and the terminal mode to restore.
+struct InputThread {
+    stop: Arc<AtomicBool>,
+    original: Termios,
+}
+
+/// Enables or disables keyboard input while progress is displayed.
+///
+/// Input is disabled by default. Once enabled, it is read whenever the
+/// display is running in [`ProgressOutput::UI`] mode and stdin is a terminal.
+/// Disabling it stops the input thread and restores the terminal mode.
+///
+/// ```rust,no_run
+/// use clx::progress::{ProgressJobBuilder, set_keyboard_input};
+///
+/// set_keyboard_input(true);
+/// let job = ProgressJobBuilder::new().prop("message", "Press v to collapse").start();
+/// ```
+pub fn set_keyboard_input(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if enabled {
+        if *STARTED.lock().unwrap() {
+            start_input();
+        }
+    } else {
+        stop_input();
+    }
+}
+
+/// Sets a handler called with every key read while progress is displayed.
+///
+/// The handler runs on the input thread before the built-in actions; return
+/// `true` to mark the key as handled and skip them. It should return quickly,
+/// since no further keys are read until it does. Replaces any previous handler.
+///
+/// ```rust,no_run
+/// use clx::progress::{Key, on_key, set_keyboard_input};
+///
+/// set_keyboard_input(true);
+/// on_key(|key| match key {
+///     Key::Char('p') => {
+///         // toggle something in the application
+///         true
+///     }
+///     _ => false,
+/// });
+/// ```
+pub fn on_key<F>(handler: F)
+where
+    F: Fn(Key) -> bool + Send + Sync + 'static,
+{
+    *KEY_HANDLER.write().unwrap() = Some(Arc::new(handler));
+}
+
+/// Returns `true` while the input thread is reading keys.
+pub(crate) fn is_reading() -> bool {
+    INPUT.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Returns `true` if `v` has collapsed the children of top-level jobs and
+/// keys are still being read.
+pub(crate) fn children_collapsed() -> bool {
+    COLLAPSED.load(Ordering::Relaxed) && is_reading()
+}
+
+/// Starts the input thread if input is enabled and not already being read.
+///
+/// Called by the refresh thread's `start`.
+pub(crate) fn start_input() {
+    if !ENABLED.load(Ordering::Relaxed) || output() != ProgressOutput::UI {
+        return;
+    }
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return;
+    }
+    let mut input = INPUT.lock().unwrap();
+    if input.is_some() {
+        return;
+    }
+    let Ok(original) = tcgetattr(&stdin) else {
+        return;
+    };
+    let mut termios = original.clone();
+    termios
+        .local_flags
+        .remove(LocalFlags::ECHO | LocalFlags::ICANON);
+    if tcsetattr(&stdin, SetArg::TCSADRAIN, &termios).is_err() {
+        return;
+    }
+    // Restores the terminal mode, since the process may exit without `stop`.
+    super::panic::install_hook();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let spawned = thread::Builder::new()
+        .name("clx-input".into())
+        .spawn(move || read_keys(&thread_stop));
+    if spawned.is_err() {
+        let _ = tcsetattr(&stdin, SetArg::TCSADRAIN, &original);
+        return;
+    }
+    *input = Some(InputThread { stop, original });
+}
+
+/// Stops the input thread and restores the terminal mode.
+pub(crate) fn stop_input() {
+    // Also called from the panic hook, so a poisoned lock is still used.
+    let input = INPUT.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(input) = input {
+        input.stop.store(true, Ordering::Relaxed);
+        let _ = tcsetattr(io::stdin(), SetArg::TCSADRAIN, &input.original);
+    }
+}
+
+/// Waits for a read in progress on the input thread to finish.
+///
+/// Called by prompts once they have the terminal; from then on the thread
+/// leaves stdin alone until they close, so no key meant for them is lost.
+pub(crate) fn wait_for_reader() {
+    drop(READING.lock().unwrap_or_else(|e| e.into_inner()));
+}
+
+fn read_keys(stop: &AtomicBool) {
+    let stdin = io::stdin();
+    let mut buf = [0; 64];
+    while !stop.load(Ordering::Relaxed) {
+        if !*STARTED.lock().unwrap() {
+            break;
+        }
+        let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::from(POLL_TIMEOUT_MS)) {
+            Ok(0) => continue,
+            Ok(_) => {}
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(_) => break,
+        }
+        // A prompt reads its own keys while the display is paused. The check
+        // and the read happen under `READING`, which the prompt waits for.
+        let read = {
+            let _reading = READING.lock().unwrap();
+            (!is_paused() && !is_prompting()).then(|| nix::unistd::read(stdin.as_fd(), &mut buf))
+        };
+        let Some(read) = read else {
+            thread::sleep(std::time::Duration::from_millis(POLL_TIMEOUT_MS.into()));
+            continue;
+        };
+        let count = match read {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(_) => break,
+        };
+        for key in parse_keys(&buf[..count]) {
+            dispatch(key);
+        }
+    }
+    if !stop.load(Ordering::Relaxed) {
+        stop_input();
+    }
+}
+
+fn dispatch(key: Key) {
+    let handler = KEY_HANDLER.read().unwrap().clone();
+    if handler.is_some_and(|handler| handler(key)) {
+        return;
+    }
+    let page = (super::term().size().0 as usize).saturating_sub(2).max(1);
+    match key {
+        Key::Char('v') => {
+            COLLAPSED.fetch_xor(true, Ordering::Relaxed);
+        }
+        Key::Char('q') => {
+            for job in JOBS.lock().unwrap().clone() {
+                job.cancel();
+            }
+            return;
+        }
+        Key::Up => scroll_by(-1),
+        Key::Down => scroll_by(1),
+        Key::PageUp => scroll_by(-(page as isize)),
+        Key::PageDown => scroll_by(page as isize),
+        // Clamped to the last page when the frame is clipped.
+        Key::Home => SCROLL.store(0, Ordering::Relaxed),
+        Key::End => SCROLL.store(usize::MAX, Ordering::Relaxed),
+        _ => return,
+    }
+    notify();
+}
+
+fn scroll_by(delta: isize) {
+    let _ = SCROLL.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |offset| {
+        Some(offset.saturating_add_signed(delta))
+    });
+}
+
+/// Decodes the bytes read from the terminal into keys.
+///
+/// Unrecognized escape sequences and control characters are dropped.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b => {
+                let (key, len) = parse_escape(&bytes[i..]);
+                keys.extend(key);
+                i += len;
+                continue;
+            }
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            b'\t' => keys.push(Key::Tab),
+            0x7f | 0x08 => keys.push(Key::Backspace),
+            b if b < 0x20 => {}
+            b => {
+                let len = utf8_len(b).min(bytes.len() - i);
+                if let Some(c) = std::str::from_utf8(&bytes[i..i + len])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    keys.push(Key::Char(c));
+                }
+                i += len;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// Decodes an escape sequence at the start of `bytes`, returning the key and
+/// the number of bytes consumed.
+fn parse_escape(bytes: &[u8]) -> (Option<Key>, usize) {
+    match bytes.get(1) {
+        None | Some(0x1b) => (Some(Key::Escape), 1),
+        Some(b'[' | b'O') => {
+            let Some(end) = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+                return (None, bytes.len());
+            };
+            let params = &bytes[2..2 + end];
+            let key = match (params, bytes[2 + end]) {
+                (_, b'A') => Some(Key::Up),
+                (_, b'B') => Some(Key::Down),
+                (_, b'C') => Some(Key::Right),
+                (_, b'D') => Some(Key::Left),
+                (_, b'H') | (b"1" | b"7", b'~') => Some(Key::Home),
+                (_, b'F') | (b"4" | b"8", b'~') => Some(Key::End),
+                (b"5", b'~') => Some(Key::PageUp),
+                (b"6", b'~') => Some(Key::PageDown),
+                _ => None,
+            };
+            (key, end + 3)
+        }
+        // Alt+key; the key itself is read on its own.
+        Some(_) => (None, 1),
+    }
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    }
+}
+
+/// Clips a frame taller than the terminal to a scrolled window of its lines,
+/// followed by a line showing which part is visible.
+///
+/// Only applied while keys are being read, so the window can be scrolled.
+pub(crate) fn scroll_frame(output: String, term_height: usize) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    // Leave a row for the indicator and one for the cursor below the frame.
+    let visible = term_height.saturating_sub(2).max(1);
+    if lines.len() < term_height {
+        return output;
+    }
+    let max_offset = lines.len() - visible;
+    let offset = SCROLL.load(Ordering::Relaxed).min(max_offset);
+    SCROLL.store(offset, Ordering::Relaxed);
+    let end = offset + visible;
+    let indicator = style::edim(format!(
+        "↑↓ {}-{} of {} lines",
+        offset + 1,
+        end,
+        lines.len()
+    ));
+    format!("{}\n{indicator}", lines[offset..end].join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"vq\r\t\x7f"),
+            [
+                Key::Char('v'),
+                Key::Char('q'),
+                Key::Enter,
+                Key::Tab,
+                Key::Backspace
+            ]
+        );
+        assert_eq!(parse_keys("é".as_bytes()), [Key::Char('é')]);
+        assert_eq!(parse_keys(b"\x03"), []);
+    }
+
+    #[test]
+    fn test_parse_escape_sequences() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1b[B\x1bOC\x1b[D"),
+            [Key::Up, Key::Down, Key::Right, Key::Left]
+        );
+        assert_eq!(
+            parse_keys(b"\x1b[5~\x1b[6~\x1b[H\x1b[4~"),
+            [Key::PageUp, Key::PageDown, Key::Home, Key::End]
+        );
+        assert_eq!(parse_keys(b"\x1b"), [Key::Escape]);
+        assert_eq!(parse_keys(b"\x1b[3~j"), [Key::Char('j')]);
+    }
+
+    #[test]
+    fn test_scroll_frame() {
+        let frame = (1..=10)
+            .map(|i| format!("job {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(scroll_frame(frame.clone(), 20), frame);
+
+        SCROLL.store(usize::MAX, Ordering::Relaxed);
+        let clipped = scroll_frame(frame, 5);
+        assert_eq!(
+            console::strip_ansi_codes(&clipped),
+            "job 8\njob 9\njob 10\n↑↓ 8-10 of 10 lines"
+        );
+        assert_eq!(SCROLL.load(Ordering::Relaxed), 7);
+        SCROLL.store(0, Ordering::Relaxed);
+    }
+}
@@ -1,29 +1,38 @@
 //! Progress job types and builder.
 
 use std::fmt;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use serde::ser::Serialize as SerializeTrait;
-use tera::{Context, Tera};
 
-use crate::Result;
+use crate::{Error, Result, style};
 
+use super::atomic::{AtomicOptionF64, AtomicOptionUsize};
+use super::body::IntoBody;
+use super::cancel::CancellationToken;
+use super::error_handler::report_error;
+use super::events::JobEvents;
 use super::flex::flex;
+use super::functions::RenderState;
+use super::history;
 use super::output::{ProgressOutput, output};
-use super::render::{RenderContext, add_tera_template, indent, render_text_mode};
+use super::panic::JobGuard;
+use super::render::{RenderContext, indent, render_text_mode};
 use super::spinners::DEFAULT_BODY;
 use super::state::{
-    JOBS, LAST_OUTPUT, REFRESH_LOCK, STOPPING, SyncUpdate, TERM_LOCK, is_disabled, notify, term,
+    JOBS, LAST_OUTPUT, REFRESH_LOCK, STOPPING, SyncUpdate, TEMPLATES, TERM_LOCK,
+    hold_while_prompting, is_disabled, notify, term,
 };
-use super::tera_setup::add_tera_functions;
+use super::template::{Context, TemplateEngine, Templates};
 
 /// Status of a progress job.
 ///
 /// The status determines how the job is displayed (spinner icon, colors) and
 /// whether it's considered "active" (still running).
 #[derive(Debug, Default, Clone, PartialEq, strum::EnumIs)]
+#[non_exhaustive]
 pub enum ProgressStatus {
     /// Hidden status - the job is not displayed.
     Hide,
@@ -42,6 +51,10 @@ pub enum ProgressStatus {
     Warn,
     /// Failed - shows a red X.
     Failed,
+    /// Cancelled before it finished - shows a dimmed ⊘.
+    ///
+    /// Set by [`ProgressJob::cancel`] on a job and its unfinished descendants.
+    Cancelled,
 }
 
 impl ProgressStatus {
@@ -49,10 +62,19 @@ impl ProgressStatus {
     pub fn is_active(&self) -> bool {
         matches!(self, Self::Running | Self::RunningCustom(_))
     }
+
+    /// Returns `true` for the final statuses a job ends with.
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Self::Done | Self::DoneCustom(_) | Self::Warn | Self::Failed | Self::Cancelled
+        )
+    }
 }
 
 /// Behavior when a progress job completes.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum ProgressJobDoneBehavior {
     /// Keep the job and all children visible (default).
     #[default]
@@ -61,18 +83,53 @@ pub enum ProgressJobDoneBehavior {
     Collapse,
     /// Remove the job from display entirely.
     Hide,
+    /// Print the job's final frame once above the live display, into normal
+    /// scrollback, and remove the job from the frame.
+    ///
+    /// Use this for long lists of jobs where finished entries only need to be
+    /// recorded, not redrawn on every refresh.
+    Print,
+}
+
+/// Number of log lines shown beneath a running job by default.
+const DEFAULT_LOG_LINES: usize = 3;
+
+/// Callback invoked with a job's render errors.
+pub(crate) type RenderErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// Render errors waiting for their job's `on_render_error` callback.
+///
+/// Jobs render with the template cache and often `REFRESH_LOCK` held, and a
+/// callback that updates its job takes those again, so callbacks only run
+/// from [`run_render_error_handlers`] once the renderer has let go of them.
+static PENDING_RENDER_ERRORS: Mutex<Vec<(RenderErrorHandler, Error)>> = Mutex::new(vec![]);
+
+/// Runs the `on_render_error` callbacks for errors collected while rendering.
+///
+/// Must be called with none of the display locks held.
+pub(crate) fn run_render_error_handlers() {
+    let pending = std::mem::take(&mut *PENDING_RENDER_ERRORS.lock().unwrap());
+    for (handler, err) in pending {
+        handler(&err);
+    }
 }
 
 /// Builder for creating progress jobs.
 #[must_use]
 pub struct ProgressJobBuilder {
     body: String,
+    /// Why the [`Body`](super::Body) passed to `body()` has no template.
+    body_error: Option<String>,
     body_text: Option<String>,
     status: ProgressStatus,
+    engine: TemplateEngine,
     ctx: Context,
     on_done: ProgressJobDoneBehavior,
     progress_current: Option<usize>,
     progress_total: Option<usize>,
+    min_update_interval: Option<Duration>,
+    log_lines: usize,
+    on_render_error: Option<RenderErrorHandler>,
 }
 
 impl Default for ProgressJobBuilder {
@@ -87,9 +144,12 @@ impl fmt::Debug for ProgressJobBuilder {
             .field("body", &self.body)
             .field("body_text", &self.body_text)
             .field("status", &self.status)
+            .field("engine", &self.engine)
             .field("on_done", &self.on_done)
             .field("progress_current", &self.progress_current)
             .field("progress_total", &self.progress_total)
+            .field("min_update_interval", &self.min_update_interval)
+            .field("log_lines", &self.log_lines)
             .finish_non_exhaustive()
     }
 }
@@ -99,18 +159,34 @@ impl ProgressJobBuilder {
     pub fn new() -> Self {
         Self {
             body: DEFAULT_BODY.clone(),
+            body_error: None,
             body_text: None,
             status: Default::default(),
+            engine: Default::default(),
             ctx: Default::default(),
             on_done: Default::default(),
             progress_current: None,
             progress_total: None,
+            min_update_interval: None,
+            log_lines: DEFAULT_LOG_LINES,
+            on_render_error: None,
         }
     }
 
-    /// Sets the Tera template for rendering the job body.
-    pub fn body<S: Into<String>>(mut self, body: S) -> Self {
-        self.body = body.into();
+    /// Sets the template for rendering the job body, as a string or a
+    /// [`Body`](super::Body).
+    ///
+    /// A `Body` with an invalid part is reported by
+    /// [`try_build`](Self::try_build), or by rendering the job, as an invalid
+    /// template string is.
+    pub fn body(mut self, body: impl IntoBody) -> Self {
+        match body.into_template() {
+            Ok(body) => {
+                self.body = body;
+                self.body_error = None;
+            }
+            Err(err) => self.body_error = Some(err),
+        }
         self
     }
 
@@ -120,6 +196,15 @@ impl ProgressJobBuilder {
         self
     }
 
+    /// Sets the template engine used to render the body.
+    ///
+    /// Defaults to [`TemplateEngine::Tera`] when the `tera` feature is enabled
+    /// and to [`TemplateEngine::Simple`] otherwise.
+    pub fn engine(mut self, engine: TemplateEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
     /// Sets the initial status of the job.
     pub fn status(mut self, status: ProgressStatus) -> Self {
         self.status = status;
@@ -144,120 +229,241 @@ impl ProgressJobBuilder {
         self.prop("total", &progress_total)
     }
 
+    /// Sets the minimum time between display updates triggered by this job.
+    ///
+    /// Updates that arrive sooner only mark the job dirty. In UI mode the
+    /// background refresh picks up the latest state on its next frame; in text
+    /// mode it is written by the next update after the interval has passed, by
+    /// a terminal status, or by [`flush`](super::flush). Use this for jobs
+    /// updated from hot loops, where rendering every `increment()` would cost
+    /// more than the work being tracked.
+    pub fn min_update_interval(mut self, interval: Duration) -> Self {
+        self.min_update_interval = Some(interval);
+        self
+    }
+
+    /// Sets how many of the most recent [`log`](ProgressJob::log) lines are
+    /// shown beneath the job while it runs. Defaults to 3; `0` hides them.
+    pub fn log_lines(mut self, n: usize) -> Self {
+        self.log_lines = n;
+        self
+    }
+
     /// Sets a template property (variable).
     pub fn prop<T: SerializeTrait + ?Sized, S: Into<String>>(mut self, key: S, val: &T) -> Self {
         self.ctx.insert(key.into(), val);
         self
     }
 
+    /// Sets a callback for errors raised while rendering this job's body.
+    ///
+    /// Without one, a render error aborts the whole frame and is passed to the
+    /// handler set with [`set_error_handler`](super::set_error_handler), which
+    /// logs it with `log::error!` or prints it to stderr by default. With one,
+    /// the error is passed to the callback instead and the job's own line is
+    /// left out of the display until it renders again; other jobs, including
+    /// its children, keep rendering. The callback runs once per distinct error
+    /// rather than on every frame, and may be called from the background
+    /// refresh thread. It runs after rendering has released the display, so it
+    /// may update the job, for example to mark it failed.
+    pub fn on_render_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.on_render_error = Some(Arc::new(handler));
+        self
+    }
+
     /// Builds the progress job without starting it.
     #[must_use = "the returned ProgressJob should be used or stored"]
     pub fn build(self) -> ProgressJob {
         static ID: AtomicUsize = AtomicUsize::new(0);
+        let finished = self.status.is_finished().then(Instant::now);
+        let events = JobEvents::new(&self.status);
         ProgressJob {
             id: ID.fetch_add(1, Ordering::Relaxed),
             body: Mutex::new(self.body),
+            body_error: Mutex::new(self.body_error),
             body_text: self.body_text,
             status: Mutex::new(self.status),
+            engine: self.engine,
             on_done: self.on_done,
             parent: Weak::new(),
             children: Mutex::new(vec![]),
             tera_ctx: Mutex::new(self.ctx),
-            progress_current: Mutex::new(self.progress_current),
-            progress_total: Mutex::new(self.progress_total),
+            progress_current: AtomicOptionUsize::new(self.progress_current),
+            progress_total: AtomicOptionUsize::new(self.progress_total),
             start: Instant::now(),
+            finished: Mutex::new(finished),
             last_progress_update: Mutex::new(None),
-            smoothed_rate: Mutex::new(None),
-            operations_total: Mutex::new(None),
-            operation_index: Mutex::new(0),
+            smoothed_rate: AtomicOptionF64::new(None),
+            operations_total: AtomicOptionUsize::new(None),
+            operation_index: AtomicUsize::new(0),
             operation_start: Mutex::new(Instant::now()),
             last_text_output: Mutex::new(None),
-            last_compiled_body: Mutex::new(None),
+            min_update_interval: self.min_update_interval,
+            last_update: Mutex::new(None),
+            dirty: AtomicBool::new(false),
+            log: Mutex::new(vec![]),
+            log_lines: self.log_lines,
+            on_render_error: self.on_render_error,
+            last_render_error: Mutex::new(None),
+            cancel_token: CancellationToken::new(),
+            events,
         }
     }
 
+    /// Builds the progress job after checking that its templates compile.
+    ///
+    /// Unlike [`build`](Self::build), which defers every template error to
+    /// render time, this reports syntax errors, unknown functions or filters,
+    /// and unknown literal spinner names up front. Variables are not checked,
+    /// since props may still be set after the job is built.
+    #[must_use = "the returned ProgressJob should be used or stored"]
+    pub fn try_build(self) -> Result<ProgressJob> {
+        if let Some(err) = &self.body_error {
+            return Err(Error::Template(err.clone()));
+        }
+        {
+            let mut templates = TEMPLATES.lock().unwrap();
+            let templates = templates.get_or_insert_with(Templates::new);
+            templates.compile(self.engine, &self.body)?;
+            if let Some(body_text) = &self.body_text {
+                templates.compile(self.engine, body_text)?;
+            }
+        }
+        Ok(self.build())
+    }
+
     /// Builds and starts the progress job as a top-level job.
     #[must_use = "the returned job handle is needed to control the job"]
     pub fn start(self) -> Arc<ProgressJob> {
-        let job = Arc::new(self.build());
-        JOBS.lock().unwrap().push(job.clone());
-        job.update();
-        job
+        start_job(self.build())
+    }
+
+    /// Like [`start`](Self::start), but checks the templates first as
+    /// [`try_build`](Self::try_build) does.
+    #[must_use = "the returned job handle is needed to control the job"]
+    pub fn try_start(self) -> Result<Arc<ProgressJob>> {
+        Ok(start_job(self.try_build()?))
     }
 }
 
+/// Registers a built job as a top-level job and shows it.
+pub(crate) fn start_job(job: ProgressJob) -> Arc<ProgressJob> {
+    let job = Arc::new(job);
+    JOBS.lock().unwrap().push(job.clone());
+    job.update();
+    job
+}
+
 /// A progress job handle for updating and controlling an active progress indicator.
 pub struct ProgressJob {
     pub(crate) id: usize,
     pub(crate) body: Mutex<String>,
+    /// Set instead of a usable `body` by an invalid [`Body`](super::Body).
+    pub(crate) body_error: Mutex<Option<String>>,
     pub(crate) body_text: Option<String>,
     pub(crate) status: Mutex<ProgressStatus>,
+    pub(crate) engine: TemplateEngine,
     pub(crate) parent: Weak<ProgressJob>,
     pub(crate) children: Mutex<Vec<Arc<ProgressJob>>>,
     pub(crate) tera_ctx: Mutex<Context>,
     pub(crate) on_done: ProgressJobDoneBehavior,
-    pub(crate) progress_current: Mutex<Option<usize>>,
-    pub(crate) progress_total: Mutex<Option<usize>>,
+    pub(crate) progress_current: AtomicOptionUsize,
+    pub(crate) progress_total: AtomicOptionUsize,
     pub(crate) start: Instant,
+    /// When the job reached a final status, cleared if it runs again.
+    pub(crate) finished: Mutex<Option<Instant>>,
     /// Last progress update time and value (for rate calculation)
     pub(crate) last_progress_update: Mutex<Option<(Instant, usize)>>,
     /// Exponentially smoothed rate (items per second)
-    pub(crate) smoothed_rate: Mutex<Option<f64>>,
+    pub(crate) smoothed_rate: AtomicOptionF64,
     /// Multi-operation tracking: total number of operations
-    pub(crate) operations_total: Mutex<Option<usize>>,
+    pub(crate) operations_total: AtomicOptionUsize,
     /// Multi-operation tracking: current operation index (0-indexed)
-    pub(crate) operation_index: Mutex<usize>,
+    pub(crate) operation_index: AtomicUsize,
     /// Start time of the current operation (for ETA calculation after next_operation)
     pub(crate) operation_start: Mutex<Instant>,
     /// Last rendered text-mode line. Used to suppress consecutive identical
     /// emissions when multiple props are updated in quick succession.
     pub(crate) last_text_output: Mutex<Option<String>>,
-    /// Last compiled Tera template body. Used to skip recompiling when the
-    /// body has not changed since the last render frame.
-    pub(crate) last_compiled_body: Mutex<Option<String>>,
+    /// Minimum time between display updates triggered by this job.
+    pub(crate) min_update_interval: Option<Duration>,
+    /// When this job last triggered a display update (for throttling).
+    pub(crate) last_update: Mutex<Option<Instant>>,
+    /// Set when a throttled update was skipped and has not been displayed yet.
+    pub(crate) dirty: AtomicBool,
+    /// Lines passed to `log()`, kept until the job finishes.
+    pub(crate) log: Mutex<Vec<String>>,
+    /// How many log lines to show beneath the job while it runs.
+    pub(crate) log_lines: usize,
+    pub(crate) on_render_error: Option<RenderErrorHandler>,
+    /// Last error passed to `on_render_error`, so each is reported only once.
+    pub(crate) last_render_error: Mutex<Option<String>>,
+    /// Cancelled with this job; linked to the parent job's token by `add`.
+    pub(crate) cancel_token: CancellationToken,
+    /// Status history and the callbacks registered with `on_*`.
+    pub(crate) events: JobEvents,
 }
 
 impl ProgressJob {
-    /// Renders this job to a string using the given Tera engine and context.
-    pub(crate) fn render(&self, tera: &mut Tera, mut ctx: RenderContext) -> Result<String> {
+    /// Renders this job to a string using the given template cache and context.
+    pub(crate) fn render(
+        &self,
+        templates: &mut Templates,
+        mut ctx: RenderContext,
+    ) -> Result<String> {
         let mut s = vec![];
         ctx.tera_ctx.extend(self.tera_ctx.lock().unwrap().clone());
-        ctx.progress = if let (Some(progress_current), Some(progress_total)) = (
-            *self.progress_current.lock().unwrap(),
-            *self.progress_total.lock().unwrap(),
-        ) {
-            Some((progress_current, progress_total))
-        } else {
-            None
-        };
-        add_tera_functions(tera, &ctx, self);
+        // The counters are read here rather than written to the context on
+        // every update, keeping `increment()` free of the context lock.
+        let progress_current = self.progress_current.load();
+        let progress_total = self.progress_total.load();
+        if let Some(current) = progress_current {
+            ctx.tera_ctx.insert("cur", &current);
+        }
+        if let Some(total) = progress_total {
+            ctx.tera_ctx.insert("total", &total);
+        }
+        ctx.progress = progress_current.zip(progress_total);
         if !self.should_display() {
             return Ok(String::new());
         }
-        let body = if output() == ProgressOutput::Text {
-            self.body_text
-                .clone()
-                .unwrap_or(self.body.lock().unwrap().clone())
-        } else {
-            self.body.lock().unwrap().clone()
+        let body = match &self.body_text {
+            Some(body_text) if output() == ProgressOutput::Text => Ok(body_text.clone()),
+            _ => match self.body_error.lock().unwrap().clone() {
+                Some(err) => Err(Error::Template(err)),
+                None => Ok(self.body.lock().unwrap().clone()),
+            },
         };
-        let name = format!("progress_{}", self.id);
-        {
-            let mut last = self.last_compiled_body.lock().unwrap();
-            if last.as_deref() != Some(&body) {
-                add_tera_template(tera, &name, &body)?;
-                *last = Some(body.clone());
+        let state = RenderState::new(&ctx, self);
+        let rendered =
+            body.and_then(|body| templates.render(self.engine, &body, &mut ctx.tera_ctx, &state));
+        match rendered {
+            Ok(rendered_body) => {
+                if self.on_render_error.is_some() {
+                    *self.last_render_error.lock().unwrap() = None;
+                }
+                let flex_width = ctx.width.saturating_sub(ctx.indent);
+                let body = flex(&rendered_body, flex_width);
+                s.push(body.trim_end().to_string());
+            }
+            Err(err) => self.handle_render_error(err)?,
+        }
+        if ctx.include_children && self.log_lines > 0 && self.is_running() {
+            let log = self.log.lock().unwrap();
+            let width = ctx.width.saturating_sub(ctx.indent + 1);
+            for line in &log[log.len().saturating_sub(self.log_lines)..] {
+                let line = console::truncate_str(line, width, "…");
+                s.push(format!(" {}", style::edim(line)));
             }
         }
-        let rendered_body = tera.render(&name, &ctx.tera_ctx)?;
-        let flex_width = ctx.width.saturating_sub(ctx.indent);
-        let body = flex(&rendered_body, flex_width);
-        s.push(body.trim_end().to_string());
-        if ctx.include_children && self.should_display_children() {
+        if ctx.include_children && !ctx.collapse_children && self.should_display_children() {
             ctx.indent += 1;
             let children = self.children.lock().unwrap();
             for child in children.iter() {
-                let child_output = child.render(tera, ctx.clone())?;
+                let child_output = child.render(templates, ctx.clone())?;
                 if !child_output.is_empty() {
                     let child_output = indent(child_output, ctx.width - ctx.indent + 1, ctx.indent);
                     s.push(child_output);
@@ -267,18 +473,42 @@ impl ProgressJob {
         Ok(s.join("\n"))
     }
 
+    /// Queues a render error for the job's `on_render_error` callback, or
+    /// returns it when there is none.
+    fn handle_render_error(&self, err: Error) -> Result<()> {
+        let Some(handler) = &self.on_render_error else {
+            return Err(err);
+        };
+        let message = err.to_string();
+        let mut last = self.last_render_error.lock().unwrap();
+        if last.as_deref() != Some(message.as_str()) {
+            *last = Some(message);
+            drop(last);
+            PENDING_RENDER_ERRORS
+                .lock()
+                .unwrap()
+                .push((handler.clone(), err));
+        }
+        Ok(())
+    }
+
     fn should_display(&self) -> bool {
         let status = self.status.lock().unwrap();
         !status.is_hide() && (status.is_active() || self.on_done != ProgressJobDoneBehavior::Hide)
     }
 
     fn should_display_children(&self) -> bool {
-        self.status.lock().unwrap().is_active() || self.on_done == ProgressJobDoneBehavior::Keep
+        self.status.lock().unwrap().is_active()
+            || matches!(
+                self.on_done,
+                ProgressJobDoneBehavior::Keep | ProgressJobDoneBehavior::Print
+            )
     }
 
     /// Adds a child job to this job.
     pub fn add(self: &Arc<Self>, mut job: ProgressJob) -> Arc<Self> {
         job.parent = Arc::downgrade(self);
+        job.cancel_token.link(&self.cancel_token);
         let job = Arc::new(job);
         self.children.lock().unwrap().push(job.clone());
         job.update();
@@ -298,41 +528,261 @@ impl ProgressJob {
         }
     }
 
+    /// Returns the job's id, which identifies it in
+    /// [`ProgressEvent`](super::ProgressEvent)s.
+    #[must_use]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     /// Returns a clone of the children jobs list.
     #[must_use]
     pub fn children(&self) -> Vec<Arc<Self>> {
         self.children.lock().unwrap().clone()
     }
 
+    /// Returns the job's `message` property, or `job <id>` if it has none,
+    /// for reports that name jobs outside the frame.
+    pub(crate) fn label(&self) -> String {
+        self.tera_ctx
+            .lock()
+            .unwrap()
+            .get("message")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("job {}", self.id))
+    }
+
+    /// Returns how long the job has run: until now while it runs, and until it
+    /// finished once it reaches a final status.
+    ///
+    /// This is also what the `elapsed()` template function shows, so a done
+    /// job kept on screen stops counting.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.finished_at()
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(self.start)
+    }
+
+    /// Returns when the job reached a final status, or `None` while it runs.
+    ///
+    /// Setting a running status again clears it.
+    #[must_use]
+    pub fn finished_at(&self) -> Option<Instant> {
+        *self.finished.lock().unwrap()
+    }
+
     /// Returns `true` if the job is still running (active).
     pub fn is_running(&self) -> bool {
         self.status.lock().unwrap().is_active()
     }
 
+    /// Cancels this job and all of its descendants.
+    ///
+    /// Their cancellation tokens report cancelled, and those that are running
+    /// or pending are marked [`Cancelled`](ProgressStatus::Cancelled). Work
+    /// is not interrupted; it should poll [`is_cancelled`](Self::is_cancelled)
+    /// and stop on its own.
+    ///
+    /// ```rust,no_run
+    /// use clx::progress::ProgressJobBuilder;
+    ///
+    /// let job = ProgressJobBuilder::new().prop("message", "Indexing").start();
+    /// let worker = job.clone();
+    /// std::thread::spawn(move || {
+    ///     while !worker.is_cancelled() {
+    ///         // do a unit of work
+    ///     }
+    /// });
+    /// job.cancel();
+    /// ```
+    pub fn cancel(&self) {
+        self.cancel_token.cancel();
+        for child in self.children() {
+            child.mark_cancelled();
+        }
+        if self.is_unfinished() {
+            self.set_status(ProgressStatus::Cancelled);
+        } else {
+            self.update();
+        }
+    }
+
+    /// Marks this job as the one the current thread is working on until the
+    /// returned guard is dropped.
+    ///
+    /// With [`install_panic_hook`](super::install_panic_hook), a panic on the
+    /// thread marks the innermost entered job as
+    /// [`Failed`](ProgressStatus::Failed).
+    pub fn enter(self: &Arc<Self>) -> JobGuard {
+        super::panic::enter(self.clone())
+    }
+
+    /// Returns `true` if this job or one of its ancestors was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+
+    /// Returns this job's cancellation token, for work that should stop when
+    /// the job is cancelled but does not hold the job itself.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+
+    /// Marks this job and its unfinished descendants as cancelled without
+    /// rendering, for callers that draw one frame afterwards.
+    pub(crate) fn mark_cancelled(&self) {
+        self.cancel_token.cancel();
+        let mut status = self.status.lock().unwrap();
+        if status.is_active() || *status == ProgressStatus::Pending {
+            let old = std::mem::replace(&mut *status, ProgressStatus::Cancelled);
+            drop(status);
+            self.record_finished_at(&ProgressStatus::Cancelled);
+            self.finish_status_change(&old, &ProgressStatus::Cancelled);
+        } else {
+            drop(status);
+        }
+        for child in self.children() {
+            child.mark_cancelled();
+        }
+    }
+
+    fn is_unfinished(&self) -> bool {
+        let status = self.status.lock().unwrap();
+        status.is_active() || *status == ProgressStatus::Pending
+    }
+
     /// Replaces the job's Tera template body.
     pub fn set_body<S: Into<String>>(&self, body: S) {
         *self.body.lock().unwrap() = body.into();
+        *self.body_error.lock().unwrap() = None;
         self.update();
     }
 
     /// Sets the job's status.
+    ///
+    /// Setting [`Cancelled`](ProgressStatus::Cancelled) also cancels the
+    /// job's cancellation token, but leaves its children's statuses alone; use
+    /// [`cancel`](Self::cancel) to cancel the whole subtree.
     pub fn set_status(&self, status: ProgressStatus) {
+        if status == ProgressStatus::Cancelled {
+            self.cancel_token.cancel();
+        }
         let mut s = self.status.lock().unwrap();
         if *s != status {
-            *s = status.clone();
+            let old = std::mem::replace(&mut *s, status.clone());
             drop(s);
-            self.update();
-            // For terminal states, do a synchronous render
-            if matches!(
-                status,
-                ProgressStatus::Done
-                    | ProgressStatus::Failed
-                    | ProgressStatus::Warn
-                    | ProgressStatus::DoneCustom(_)
-            ) {
-                let _ = super::render::refresh_once();
+            self.status_changed(&old, &status);
+        }
+    }
+
+    /// Does everything that follows a change of status, made by
+    /// [`set_status`](Self::set_status) or by the panic hook: records when the
+    /// job finished, redraws, and then completes the change as
+    /// [`finish_status_change`](Self::finish_status_change) does.
+    pub(crate) fn status_changed(&self, old: &ProgressStatus, status: &ProgressStatus) {
+        self.record_finished_at(status);
+        self.update_now();
+        // For terminal states, do a synchronous render
+        if status.is_finished()
+            && self.on_done != ProgressJobDoneBehavior::Print
+            && let Err(err) = super::render::refresh_once()
+        {
+            report_error(&err);
+        }
+        self.finish_status_change(old, status);
+    }
+
+    fn record_finished_at(&self, status: &ProgressStatus) {
+        let mut finished = self.finished.lock().unwrap();
+        if !status.is_finished() {
+            *finished = None;
+        } else if finished.is_none() {
+            // Moving between final statuses keeps the original end.
+            *finished = Some(Instant::now());
+        }
+    }
+
+    /// Completes a change of status without redrawing the frame: prints a
+    /// [`Print`](ProgressJobDoneBehavior::Print) job above the frame, prints a
+    /// failed job's log, sends the finish notification, and reports the change
+    /// to the history, callbacks, and subscribers. Cancelling through
+    /// [`mark_cancelled`](Self::mark_cancelled) calls this directly, since its
+    /// callers draw once afterwards.
+    fn finish_status_change(&self, old: &ProgressStatus, status: &ProgressStatus) {
+        if status.is_finished() {
+            let log = std::mem::take(&mut *self.log.lock().unwrap());
+            if self.on_done == ProgressJobDoneBehavior::Print {
+                self.print_to_scrollback();
             }
+            if *status == ProgressStatus::Failed {
+                self.print_log(&log);
+            }
+            if !old.is_finished() {
+                super::state::notify_job_finished(self, status);
+            }
+            history::record_finished(self);
+        } else if old.is_finished() {
+            history::forget(self.id);
         }
+        self.events
+            .status_changed(self.id, old, status, || self.progress_values());
+    }
+
+    /// Returns every status the job has had, starting with its initial one,
+    /// and when it was set.
+    #[must_use]
+    pub fn status_history(&self) -> Vec<(ProgressStatus, Instant)> {
+        self.events.history()
+    }
+
+    /// Calls `callback` with the old and new status whenever the job's status
+    /// changes.
+    ///
+    /// Callbacks run on the thread that changed the status, after the display
+    /// has been updated. They are also called when the job is cancelled by
+    /// [`cancel`](Self::cancel), by a signal, or by a panic on a thread that
+    /// [entered](Self::enter) it. In the panic case they run on a separate
+    /// thread instead, since the panicking thread may hold the display locks.
+    ///
+    /// ```rust,no_run
+    /// use clx::progress::{ProgressJobBuilder, ProgressStatus};
+    ///
+    /// let job = ProgressJobBuilder::new().prop("message", "Deploying").start();
+    /// job.on_status_change(|old, new| eprintln!("deploy: {old:?} -> {new:?}"));
+    /// job.set_status(ProgressStatus::Done);
+    /// ```
+    pub fn on_status_change<F>(&self, callback: F)
+    where
+        F: Fn(&ProgressStatus, &ProgressStatus) + Send + Sync + 'static,
+    {
+        self.events.on_status_change(Arc::new(callback));
+    }
+
+    /// Calls `callback` with the current and total progress whenever either
+    /// is updated.
+    ///
+    /// Callbacks run on the thread that updated the progress, so they should
+    /// be cheap when progress is updated often.
+    pub fn on_progress<F>(&self, callback: F)
+    where
+        F: Fn(usize, Option<usize>) + Send + Sync + 'static,
+    {
+        self.events.on_progress(Arc::new(callback));
+    }
+
+    /// Calls `callback` with the final status when the job finishes.
+    ///
+    /// A job finishes when a running or pending status is replaced by a
+    /// final one; moving between final statuses does not call it again. Like
+    /// [`on_status_change`](Self::on_status_change) callbacks, it runs on the
+    /// thread that changed the status, except when a panic fails the job.
+    pub fn on_done<F>(&self, callback: F)
+    where
+        F: Fn(&ProgressStatus) + Send + Sync + 'static,
+    {
+        self.events.on_done(Arc::new(callback));
     }
 
     /// Sets a template property (variable).
@@ -345,42 +795,60 @@ impl ProgressJob {
 
     /// Updates the current progress value.
     pub fn progress_current(&self, mut current: usize) {
-        if let Some(total) = *self.progress_total.lock().unwrap() {
+        if let Some(total) = self.progress_total.load() {
             current = current.min(total);
         }
 
         self.update_smoothed_rate(current);
 
-        *self.progress_current.lock().unwrap() = Some(current);
-        self.prop("cur", &current);
+        self.progress_current.store(Some(current));
+        self.progress_changed();
+        self.update();
     }
 
     /// Updates the total progress value.
     pub fn progress_total(&self, mut total: usize) {
-        if let Some(current) = *self.progress_current.lock().unwrap() {
+        if let Some(current) = self.progress_current.load() {
             total = total.max(current);
         }
-        *self.progress_total.lock().unwrap() = Some(total);
-        self.prop("total", &total);
+        self.progress_total.store(Some(total));
+        self.progress_changed();
+        self.update();
     }
 
     /// Increments the current progress value by the specified amount.
+    ///
+    /// This is safe to call from many threads at once: the counter is updated
+    /// with a compare-and-swap rather than under a lock.
     pub fn increment(&self, n: usize) {
-        // Hold lock throughout read-modify-write to prevent race conditions
-        let mut current_guard = self.progress_current.lock().unwrap();
-        let current = current_guard.unwrap_or(0);
-        let mut new_current = current.saturating_add(n);
-
-        if let Some(total) = *self.progress_total.lock().unwrap() {
-            new_current = new_current.min(total);
+        let mut total = None;
+        let new_current = self.progress_current.update(|current| {
+            // Read on every attempt, so a retry clamps against a total that
+            // `progress_total` set while this thread was racing.
+            total = self.progress_total.load();
+            let new_current = current.unwrap_or(0).saturating_add(n);
+            Some(total.map_or(new_current, |total| new_current.min(total)))
+        });
+
+        if let Some(new_current) = new_current {
+            self.update_smoothed_rate(new_current);
+            self.events
+                .progress_changed(self.id, new_current, total, || self.progress_values());
         }
+        self.update();
+    }
 
-        self.update_smoothed_rate(new_current);
-
-        *current_guard = Some(new_current);
-        drop(current_guard);
+    fn progress_changed(&self) {
+        let (current, total) = self.progress_values();
+        self.events
+            .progress_changed(self.id, current, total, || self.progress_values());
+    }
 
-        self.prop("cur", &new_current);
+    fn progress_values(&self) -> (usize, Option<usize>) {
+        (
+            self.progress_current.load().unwrap_or(0),
+            self.progress_total.load(),
+        )
     }
 
     /// Declares the total number of operations for multi-operation progress tracking.
@@ -425,8 +893,8 @@ impl ProgressJob {
     /// ```
     pub fn start_operations(&self, count: usize) {
         let count = count.max(1);
-        *self.operations_total.lock().unwrap() = Some(count);
-        *self.operation_index.lock().unwrap() = 0;
+        self.operations_total.store(Some(count));
+        self.operation_index.store(0, Ordering::Relaxed);
     }
 
     /// Advances to the next operation in a multi-operation sequence.
@@ -440,8 +908,8 @@ impl ProgressJob {
         // This prevents a race condition where concurrent overall_progress() calls
         // could see the new operation index with stale progress values from the
         // previous operation, causing brief incorrect progress spikes.
-        *self.progress_current.lock().unwrap() = None;
-        *self.progress_total.lock().unwrap() = None;
+        self.progress_current.store(None);
+        self.progress_total.store(None);
         {
             let mut ctx = self.tera_ctx.lock().unwrap();
             ctx.remove("cur");
@@ -450,12 +918,12 @@ impl ProgressJob {
 
         // Reset rate tracking for accurate ETA on new operation
         *self.last_progress_update.lock().unwrap() = None;
-        *self.smoothed_rate.lock().unwrap() = None;
+        self.smoothed_rate.store(None);
         // Reset operation start time so ETA fallback uses correct elapsed time
         *self.operation_start.lock().unwrap() = Instant::now();
 
         // Advance operation index after clearing progress values
-        *self.operation_index.lock().unwrap() += 1;
+        self.operation_index.fetch_add(1, Ordering::AcqRel);
 
         // Clear the text-mode dedup cache so the first render of the new
         // operation always reaches the wire, even if the rendered template
@@ -481,14 +949,14 @@ impl ProgressJob {
     /// - `Some((current, total))` - The overall progress as a tuple
     /// - `None` - No progress tracking is active
     pub fn overall_progress(&self) -> Option<(usize, usize)> {
-        let ops_total = *self.operations_total.lock().unwrap();
-        let current = *self.progress_current.lock().unwrap();
-        let total = *self.progress_total.lock().unwrap();
+        let ops_total = self.operations_total.load();
+        let current = self.progress_current.load();
+        let total = self.progress_total.load();
 
         match (ops_total, current, total) {
             // Multi-operation mode: map progress across operations
             (Some(ops), Some(cur), Some(tot)) => {
-                let op_idx = *self.operation_index.lock().unwrap();
+                let op_idx = self.operation_index.load(Ordering::Acquire);
                 // Use 1,000,000 as the scale for precision
                 let scale = 1_000_000usize;
 
@@ -509,7 +977,7 @@ impl ProgressJob {
             }
             // Multi-operation mode but no progress yet: show completed operations
             (Some(ops), None, None) | (Some(ops), Some(_), None) | (Some(ops), None, Some(_)) => {
-                let op_idx = *self.operation_index.lock().unwrap();
+                let op_idx = self.operation_index.load(Ordering::Acquire);
                 let scale = 1_000_000usize;
                 let per_op = scale as f64 / ops as f64;
                 Some((((op_idx as f64 * per_op) as usize).min(scale), scale))
@@ -522,9 +990,14 @@ impl ProgressJob {
     }
 
     /// Helper to update the smoothed rate based on progress change.
+    ///
+    /// If another thread is already recording a rate sample, this update is
+    /// skipped rather than waited on; the samples are debounced anyway.
     fn update_smoothed_rate(&self, current: usize) {
+        let Ok(mut last_update) = self.last_progress_update.try_lock() else {
+            return;
+        };
         let now = Instant::now();
-        let mut last_update = self.last_progress_update.lock().unwrap();
         if let Some((last_time, last_value)) = *last_update {
             let elapsed = now.duration_since(last_time).as_secs_f64();
             // Debounce: only update rate if at least 100ms has passed
@@ -536,11 +1009,11 @@ impl ProgressJob {
                 // Lower alpha = smoother ETA (less reactive to instantaneous changes)
                 // 0.1 means 10% weight on new rate, 90% on historical rate
                 const ALPHA: f64 = 0.1;
-                let mut smoothed = self.smoothed_rate.lock().unwrap();
-                *smoothed = Some(match *smoothed {
-                    Some(old_rate) => ALPHA * instantaneous_rate + (1.0 - ALPHA) * old_rate,
-                    None => instantaneous_rate,
-                });
+                self.smoothed_rate
+                    .store(Some(match self.smoothed_rate.load() {
+                        Some(old_rate) => ALPHA * instantaneous_rate + (1.0 - ALPHA) * old_rate,
+                        None => instantaneous_rate,
+                    }));
                 // Only update the timestamp when we actually recalculate
                 *last_update = Some((now, current));
             }
@@ -556,13 +1029,43 @@ impl ProgressJob {
     }
 
     /// Triggers a display update for this job.
+    ///
+    /// If a [minimum update interval](ProgressJobBuilder::min_update_interval)
+    /// is set and has not elapsed since the last update, the job is only marked
+    /// dirty.
     pub fn update(&self) {
+        if self.throttled() {
+            return;
+        }
+        self.update_now();
+    }
+
+    /// Returns `true` if this update falls inside the minimum update interval
+    /// and was recorded as dirty instead of displayed.
+    fn throttled(&self) -> bool {
+        let Some(interval) = self.min_update_interval else {
+            return false;
+        };
+        let now = Instant::now();
+        let mut last = self.last_update.lock().unwrap();
+        if last.is_some_and(|last| now.duration_since(last) < interval) {
+            self.dirty.store(true, Ordering::Relaxed);
+            return true;
+        }
+        *last = Some(now);
+        false
+    }
+
+    fn update_now(&self) {
+        self.dirty.store(false, Ordering::Relaxed);
         if is_disabled() || STOPPING.load(Ordering::Relaxed) || output() == ProgressOutput::Quiet {
             return;
         }
         if output() == ProgressOutput::Text {
-            if let Err(e) = render_text_mode(self) {
-                eprintln!("clx: {e:?}");
+            let rendered = render_text_mode(self);
+            run_render_error_handlers();
+            if let Err(e) = rendered {
+                report_error(&e);
             }
         } else {
             notify();
@@ -581,44 +1084,138 @@ impl ProgressJob {
             s.to_string()
         };
 
-        // In text mode, just emit the line — no frame to manage.
-        if output() == ProgressOutput::Text {
-            let _guard = TERM_LOCK.lock().unwrap();
-            let _ = term().write_line(&line);
-            return;
-        }
+        println_above_frame(&line);
+    }
 
-        // In TTY mode, pause the progress display, print the line, then
-        // redraw the frame below it.  Hold REFRESH_LOCK throughout so the
-        // background render thread cannot interleave a write_frame().
-        let _refresh_guard = REFRESH_LOCK.lock().unwrap();
-        let _sync = SyncUpdate::begin_locking();
+    /// Adds a line to this job's log.
+    ///
+    /// The most recent lines are shown beneath the job while it runs (see
+    /// [`ProgressJobBuilder::log_lines`]). If the job ends
+    /// [`Failed`](ProgressStatus::Failed), the whole log is printed above the
+    /// progress display; any other final status discards it. In text mode
+    /// nothing is shown until then, so only the output of failed jobs reaches
+    /// the log. Text containing newlines is split into separate lines.
+    pub fn log(&self, line: &str) {
+        let mut log = self.log.lock().unwrap();
+        log.extend(line.lines().map(str::to_string));
+        drop(log);
+        if self.log_lines > 0 && output() == ProgressOutput::UI {
+            self.update();
+        }
+    }
 
-        super::state::pause();
-        {
-            let _guard = TERM_LOCK.lock().unwrap();
-            let _ = term().write_line(&line);
+    /// Prints a failed job's log above the progress display, indented one
+    /// level below the job.
+    fn print_log(&self, log: &[String]) {
+        if log.is_empty() || output() == ProgressOutput::Quiet {
+            return;
         }
-        super::state::resume();
+        let depth = std::iter::successors(self.parent.upgrade(), |p| p.parent.upgrade()).count();
+        let prefix = " ".repeat(depth + 1);
+        let lines = log
+            .iter()
+            .map(|line| format!("{prefix}{line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        println_above_frame(&lines);
+    }
 
-        // Redraw the frame below the log line.
-        // Skip if the background thread has exited (STARTED=false): in that
-        // case pause() did not clear() so LINES is stale, and calling
-        // write_frame() would move the cursor to the wrong position.
-        if !*super::state::STARTED.lock().unwrap() {
+    /// Prints this job's final frame above the live display and removes it.
+    ///
+    /// Text mode has already emitted the final state as a line of its own, so
+    /// only the removal applies there.
+    fn print_to_scrollback(&self) {
+        if is_disabled() || STOPPING.load(Ordering::Relaxed) || output() != ProgressOutput::UI {
+            self.remove();
             return;
         }
-        // Inline render + write_frame (refresh_once() would deadlock on REFRESH_LOCK).
-        // If rendering fails the log line is already on screen — best-effort redraw.
-        if let Ok(frame) = super::render::render_frame() {
-            let final_output = super::render::process_flex_output(&frame.output);
-            if let Ok(written) = super::render::write_frame(&final_output, &frame.jobs) {
-                super::render::cache_written_output(
-                    &mut LAST_OUTPUT.lock().unwrap(),
-                    &final_output,
-                    written,
-                );
+        let depth = std::iter::successors(self.parent.upgrade(), |p| p.parent.upgrade()).count();
+        let rendered = {
+            let mut ctx = super::render::prepare_render_context();
+            ctx.indent = depth;
+            let mut templates = TEMPLATES.lock().unwrap();
+            let templates = templates.get_or_insert_with(Templates::new);
+            self.render(templates, ctx)
+        };
+        run_render_error_handlers();
+        self.remove();
+        match rendered {
+            Ok(rendered) if !rendered.is_empty() => {
+                let line = if depth > 0 {
+                    let width = term().size().1 as usize;
+                    indent(rendered, width.saturating_sub(depth) + 1, depth)
+                } else {
+                    rendered
+                };
+                println_above_frame(&line);
             }
+            Ok(_) => {}
+            Err(err) => report_error(&err),
+        }
+    }
+}
+
+/// Writes a line above the live progress frame and redraws the frame below it.
+fn println_above_frame(line: &str) {
+    let line = line.to_string();
+    write_above_frame(move || {
+        let _ = term().write_line(&line);
+    });
+}
+
+/// Runs `write` with the terminal lock held and the live progress frame
+/// cleared, then redraws the frame below whatever was written.
+///
+/// `write` must leave the cursor at the start of a line. While a prompt is
+/// open, `write` is held back until it closes.
+pub(crate) fn write_above_frame(write: impl FnOnce() + Send + 'static) {
+    write_above_frame_locked(write);
+    run_render_error_handlers();
+}
+
+fn write_above_frame_locked(write: impl FnOnce() + Send + 'static) {
+    // In text mode, just emit the output — no frame to manage.
+    if output() == ProgressOutput::Text {
+        let _guard = TERM_LOCK.lock().unwrap();
+        if let Some(write) = hold_while_prompting(write) {
+            write();
+        }
+        return;
+    }
+
+    // In TTY mode, pause the progress display, print the line, then
+    // redraw the frame below it.  Hold REFRESH_LOCK throughout so the
+    // background render thread cannot interleave a write_frame().
+    let _refresh_guard = REFRESH_LOCK.lock().unwrap();
+    let Some(write) = hold_while_prompting(write) else {
+        return;
+    };
+    let _sync = SyncUpdate::begin_locking();
+
+    super::state::pause();
+    {
+        let _guard = TERM_LOCK.lock().unwrap();
+        write();
+    }
+    super::state::resume();
+
+    // Redraw the frame below the written output.
+    // Skip if the background thread has exited (STARTED=false): in that
+    // case pause() did not clear() so LINES is stale, and calling
+    // write_frame() would move the cursor to the wrong position.
+    if !*super::state::STARTED.lock().unwrap() {
+        return;
+    }
+    // Inline render + write_frame (refresh_once() would deadlock on REFRESH_LOCK).
+    // If rendering fails the output is already on screen — best-effort redraw.
+    if let Ok(frame) = super::render::render_frame() {
+        let final_output = super::render::process_flex_output(&frame.output);
+        if let Ok(written) = super::render::write_frame(&final_output, &frame.jobs) {
+            super::render::cache_written_output(
+                &mut LAST_OUTPUT.lock().unwrap(),
+                &final_output,
+                written,
+            );
         }
     }
 }
@@ -651,8 +1248,25 @@ mod tests {
         let builder = ProgressJobBuilder::new();
         let job = builder.build();
         assert_eq!(*job.status.lock().unwrap(), ProgressStatus::Running);
-        assert!(job.progress_current.lock().unwrap().is_none());
-        assert!(job.progress_total.lock().unwrap().is_none());
+        assert!(job.progress_current.load().is_none());
+        assert!(job.progress_total.load().is_none());
+    }
+
+    #[test]
+    fn test_finish_time_recorded_by_status() {
+        let job = ProgressJobBuilder::new().build();
+        assert_eq!(job.finished_at(), None);
+
+        job.set_status(ProgressStatus::Done);
+        let finished = job.finished_at().expect("done job has a finish time");
+        assert_eq!(job.duration(), finished - job.start);
+
+        // A later final status keeps the first finish time.
+        job.set_status(ProgressStatus::Warn);
+        assert_eq!(job.finished_at(), Some(finished));
+
+        job.set_status(ProgressStatus::Running);
+        assert_eq!(job.finished_at(), None);
     }
 
     #[test]
@@ -666,8 +1280,8 @@ mod tests {
             .build();
 
         assert_eq!(*job.status.lock().unwrap(), ProgressStatus::Pending);
-        assert_eq!(*job.progress_current.lock().unwrap(), Some(5));
-        assert_eq!(*job.progress_total.lock().unwrap(), Some(10));
+        assert_eq!(job.progress_current.load(), Some(5));
+        assert_eq!(job.progress_total.load(), Some(10));
         assert_eq!(job.on_done, ProgressJobDoneBehavior::Hide);
     }
 
@@ -698,9 +1312,61 @@ mod tests {
         assert!(!ProgressStatus::Pending.is_active());
         assert!(!ProgressStatus::Hide.is_active());
         assert!(!ProgressStatus::Warn.is_active());
+        assert!(!ProgressStatus::Cancelled.is_active());
         assert!(!ProgressStatus::DoneCustom("custom".to_string()).is_active());
     }
 
+    #[test]
+    fn test_cancel_propagates_to_descendants() {
+        let parent = Arc::new(ProgressJobBuilder::new().build());
+        let done = parent.add(
+            ProgressJobBuilder::new()
+                .status(ProgressStatus::Done)
+                .build(),
+        );
+        let child = parent.add(ProgressJobBuilder::new().build());
+        let token = child.cancellation_token();
+        let grandchild = child.add(
+            ProgressJobBuilder::new()
+                .status(ProgressStatus::Pending)
+                .build(),
+        );
+
+        child.cancel();
+        assert!(!parent.is_cancelled());
+        assert!(token.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert_eq!(*child.status.lock().unwrap(), ProgressStatus::Cancelled);
+        assert_eq!(
+            *grandchild.status.lock().unwrap(),
+            ProgressStatus::Cancelled
+        );
+
+        parent.cancel();
+        assert!(done.is_cancelled());
+        assert_eq!(*done.status.lock().unwrap(), ProgressStatus::Done);
+        let late = parent.add(ProgressJobBuilder::new().build());
+        assert!(late.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_finishes_descendants_like_set_status() {
+        let parent = Arc::new(ProgressJobBuilder::new().build());
+        let printed = parent.add(
+            ProgressJobBuilder::new()
+                .on_done(ProgressJobDoneBehavior::Print)
+                .build(),
+        );
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let seen = finished.clone();
+        printed.on_done(move |status| seen.lock().unwrap().push(status.clone()));
+
+        parent.cancel();
+        assert!(parent.children().is_empty());
+        assert_eq!(*finished.lock().unwrap(), [ProgressStatus::Cancelled]);
+        assert!(printed.finished_at().is_some());
+    }
+
     #[test]
     fn test_progress_status_transitions() {
         let job = ProgressJobBuilder::new().build();
@@ -735,14 +1401,14 @@ mod tests {
     fn test_progress_job_progress_updates() {
         let job = ProgressJobBuilder::new().progress_total(100).build();
 
-        assert_eq!(*job.progress_total.lock().unwrap(), Some(100));
-        assert!(job.progress_current.lock().unwrap().is_none());
+        assert_eq!(job.progress_total.load(), Some(100));
+        assert!(job.progress_current.load().is_none());
 
         job.progress_current(50);
-        assert_eq!(*job.progress_current.lock().unwrap(), Some(50));
+        assert_eq!(job.progress_current.load(), Some(50));
 
         job.progress_current(150);
-        assert_eq!(*job.progress_current.lock().unwrap(), Some(100));
+        assert_eq!(job.progress_current.load(), Some(100));
     }
 
     #[test]
@@ -750,7 +1416,57 @@ mod tests {
         let job = ProgressJobBuilder::new().progress_current(80).build();
 
         job.progress_total(50);
-        assert_eq!(*job.progress_total.lock().unwrap(), Some(80));
+        assert_eq!(job.progress_total.load(), Some(80));
+    }
+
+    #[test]
+    fn test_log_shows_last_lines_while_running() {
+        let job = ProgressJobBuilder::new()
+            .body("{{ message }}")
+            .prop("message", "build")
+            .log_lines(2)
+            .build();
+        *job.log.lock().unwrap() = vec!["one".into(), "two".into(), "three".into()];
+        let ctx = RenderContext {
+            width: 80,
+            ..Default::default()
+        };
+
+        let output = job.render(&mut Templates::new(), ctx.clone()).unwrap();
+        assert_eq!(console::strip_ansi_codes(&output), "build\n two\n three");
+
+        // Collapsing children with `v` still shows the log.
+        let job = Arc::new(job);
+        job.add(
+            ProgressJobBuilder::new()
+                .body("{{ message }}")
+                .prop("message", "child")
+                .build(),
+        );
+        let collapsed_ctx = RenderContext {
+            collapse_children: true,
+            ..ctx.clone()
+        };
+        let output = job.render(&mut Templates::new(), collapsed_ctx).unwrap();
+        assert_eq!(console::strip_ansi_codes(&output), "build\n two\n three");
+
+        let text_ctx = RenderContext {
+            include_children: false,
+            ..ctx
+        };
+        assert_eq!(
+            job.render(&mut Templates::new(), text_ctx).unwrap(),
+            "build"
+        );
+    }
+
+    #[test]
+    fn test_log_is_discarded_when_done() {
+        let job = ProgressJobBuilder::new().build();
+        job.log("a\nb");
+        assert_eq!(*job.log.lock().unwrap(), ["a", "b"]);
+        job.set_status(ProgressStatus::Done);
+        assert!(job.log.lock().unwrap().is_empty());
     }
 
     #[test]
@@ -779,18 +1495,37 @@ mod tests {
         assert!(debug_str.contains("Running"));
     }
 
+    #[test]
+    fn test_min_update_interval_coalesces_updates() {
+        let job = ProgressJobBuilder::new()
+            .min_update_interval(Duration::from_secs(60))
+            .build();
+
+        job.prop("message", "first");
+        assert!(!job.dirty.load(Ordering::Relaxed));
+
+        // Updates inside the interval are only recorded
+        job.prop("message", "second");
+        job.increment(1);
+        assert!(job.dirty.load(Ordering::Relaxed));
+
+        // Terminal statuses are always displayed
+        job.set_status(ProgressStatus::Done);
+        assert!(!job.dirty.load(Ordering::Relaxed));
+    }
+
     #[test]
     fn test_start_operations() {
         let job = ProgressJobBuilder::new().build();
 
         // Initially no operations
-        assert!(job.operations_total.lock().unwrap().is_none());
-        assert_eq!(*job.operation_index.lock().unwrap(), 0);
+        assert!(job.operations_total.load().is_none());
+        assert_eq!(job.operation_index.load(Ordering::Relaxed), 0);
 
         // Start with 3 operations
         job.start_operations(3);
-        assert_eq!(*job.operations_total.lock().unwrap(), Some(3));
-        assert_eq!(*job.operation_index.lock().unwrap(), 0);
+        assert_eq!(job.operations_total.load(), Some(3));
+        assert_eq!(job.operation_index.load(Ordering::Relaxed), 0);
     }
 
     #[test]
@@ -799,7 +1534,7 @@ mod tests {
 
         // Passing 0 should be clamped to 1
         job.start_operations(0);
-        assert_eq!(*job.operations_total.lock().unwrap(), Some(1));
+        assert_eq!(job.operations_total.load(), Some(1));
     }
 
     #[test]
@@ -813,10 +1548,10 @@ mod tests {
         // Advance to next operation
         job.next_operation();
 
-        assert_eq!(*job.operation_index.lock().unwrap(), 1);
+        assert_eq!(job.operation_index.load(Ordering::Relaxed), 1);
         // Progress should be reset
-        assert!(job.progress_current.lock().unwrap().is_none());
-        assert!(job.progress_total.lock().unwrap().is_none());
+        assert!(job.progress_current.load().is_none());
+        assert!(job.progress_total.load().is_none());
     }
 
     #[test]
@@ -25,19 +25,54 @@
 //! job.set_status(ProgressStatus::Done);
 //! ```
 
-use super::state::{STARTED, TERM_LOCK, is_paused, pause, resume};
+use super::state::write_above;
 use crate::style;
+use log::kv::{self, Key, VisitSource};
 use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 use std::io::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Custom formatter for log records, set with [`ProgressLogger::format`].
+type LogFormatter = Arc<dyn Fn(&Record) -> String + Send + Sync>;
+
+/// A per-target level, parsed from a filter such as `my_crate::db=debug`.
+#[derive(Debug, Clone, PartialEq)]
+struct Directive {
+    target: String,
+    level: LevelFilter,
+}
 
 /// A logger that integrates with the progress display system.
 ///
 /// This logger wraps log output to ensure it doesn't interfere with
 /// progress display. Before each log message, progress is paused,
 /// and after the message is written, progress resumes.
+///
+/// By default each line is a colored level followed by the message and any
+/// key-value pairs. Timestamps, targets, and thread names can be added, or
+/// the whole line replaced with [`format`](Self::format):
+///
+/// ```rust,ignore
+/// use clx::progress::ProgressLogger;
+/// use log::LevelFilter;
+///
+/// ProgressLogger::new(LevelFilter::Info)
+///     .filter("warn,my_app=debug,my_app::db=trace")
+///     .timestamps(true)
+///     .targets(true)
+///     .init()
+///     .unwrap();
+/// ```
 pub struct ProgressLogger {
     level: LevelFilter,
-    target_filter: Option<String>,
+    /// Per-target levels, longest target first.
+    directives: Vec<Directive>,
+    timestamps: bool,
+    targets: bool,
+    thread_names: bool,
+    key_values: bool,
+    formatter: Option<LogFormatter>,
 }
 
 impl ProgressLogger {
@@ -49,7 +84,12 @@ impl ProgressLogger {
     pub fn new(level: LevelFilter) -> Self {
         Self {
             level,
-            target_filter: None,
+            directives: vec![],
+            timestamps: false,
+            targets: false,
+            thread_names: false,
+            key_values: true,
+            formatter: None,
         }
     }
 
@@ -62,19 +102,124 @@ impl ProgressLogger {
     /// * `level` - The maximum log level to display
     /// * `target` - Only show logs from targets starting with this prefix
     pub fn with_target(level: LevelFilter, target: impl Into<String>) -> Self {
-        Self {
+        let mut logger = Self::new(LevelFilter::Off);
+        logger.add_directive(Directive {
+            target: target.into(),
             level,
-            target_filter: Some(target.into()),
+        });
+        logger
+    }
+
+    /// Applies level filters in the `RUST_LOG` syntax used by `env_logger`.
+    ///
+    /// The spec is a comma-separated list of `target=level` directives. A bare
+    /// level sets the level for all other targets, and a bare target enables
+    /// every level for it. When several directives match, the one with the
+    /// longest target wins. Invalid directives are ignored.
+    ///
+    /// ```rust,ignore
+    /// ProgressLogger::new(LevelFilter::Info).filter("warn,my_app=debug,hyper=off");
+    /// ```
+    pub fn filter(mut self, spec: &str) -> Self {
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = level.trim().parse() {
+                        self.add_directive(Directive {
+                            target: target.trim().to_string(),
+                            level,
+                        });
+                    }
+                }
+                None => match part.parse() {
+                    Ok(level) => self.level = level,
+                    Err(_) => self.add_directive(Directive {
+                        target: part.to_string(),
+                        level: LevelFilter::Trace,
+                    }),
+                },
+            }
+        }
+        self
+    }
+
+    /// Applies level filters from an environment variable, such as `RUST_LOG`.
+    ///
+    /// See [`filter`](Self::filter) for the syntax. Does nothing if the
+    /// variable is unset.
+    pub fn filter_env(self, var: &str) -> Self {
+        match std::env::var(var) {
+            Ok(spec) => self.filter(&spec),
+            Err(_) => self,
         }
     }
 
+    /// Shows a UTC timestamp at the start of each line.
+    pub fn timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Shows the record's target, usually its module path.
+    pub fn targets(mut self, enabled: bool) -> Self {
+        self.targets = enabled;
+        self
+    }
+
+    /// Shows the name of the thread that logged the record.
+    pub fn thread_names(mut self, enabled: bool) -> Self {
+        self.thread_names = enabled;
+        self
+    }
+
+    /// Shows key-value pairs attached to the record, such as
+    /// `info!(path = "a.txt"; "copied")`. Enabled by default.
+    pub fn key_values(mut self, enabled: bool) -> Self {
+        self.key_values = enabled;
+        self
+    }
+
+    /// Formats each record with `formatter` instead of the built-in format.
+    ///
+    /// The returned string is written as one line; the filters still apply.
+    pub fn format<F>(mut self, formatter: F) -> Self
+    where
+        F: Fn(&Record) -> String + Send + Sync + 'static,
+    {
+        self.formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    fn add_directive(&mut self, directive: Directive) {
+        self.directives.retain(|d| d.target != directive.target);
+        self.directives.push(directive);
+        self.directives
+            .sort_by_key(|d| std::cmp::Reverse(d.target.len()));
+    }
+
+    /// Returns the maximum level enabled for `target`.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|d| target.starts_with(&d.target))
+            .map_or(self.level, |d| d.level)
+    }
+
+    /// Returns the most verbose level any target can log at.
+    fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|d| d.level)
+            .fold(self.level, Ord::max)
+    }
+
     /// Installs this logger as the global logger.
     ///
     /// # Errors
     ///
     /// Returns an error if a logger has already been set.
     pub fn init(self) -> Result<(), SetLoggerError> {
-        let level = self.level;
+        let level = self.max_level();
         // Set logger first to avoid modifying max level if logger installation fails
         log::set_boxed_logger(Box::new(self))?;
         log::set_max_level(level);
@@ -82,27 +227,81 @@ impl ProgressLogger {
     }
 
     fn format_message(&self, record: &Record) -> String {
-        let level_str = match record.level() {
+        if let Some(formatter) = &self.formatter {
+            return formatter(record);
+        }
+        let mut parts = vec![];
+        if self.timestamps {
+            parts.push(style::edim(format_timestamp(SystemTime::now())).to_string());
+        }
+        parts.push(match record.level() {
             Level::Error => style::ered("ERROR").to_string(),
             Level::Warn => style::eyellow("WARN").to_string(),
             Level::Info => style::ecyan("INFO").to_string(),
             Level::Debug => style::edim("DEBUG").to_string(),
             Level::Trace => style::edim("TRACE").to_string(),
-        };
-        format!("{} {}", level_str, record.args())
+        });
+        if self.thread_names {
+            let thread = std::thread::current();
+            let name = match thread.name() {
+                Some(name) => name.to_string(),
+                None => format!("{:?}", thread.id()),
+            };
+            parts.push(style::edim(format!("[{name}]")).to_string());
+        }
+        if self.targets {
+            parts.push(style::edim(format!("{}:", record.target())).to_string());
+        }
+        parts.push(record.args().to_string());
+        if self.key_values {
+            let mut kvs = KeyValues(vec![]);
+            let _ = record.key_values().visit(&mut kvs);
+            parts.extend(kvs.0);
+        }
+        parts.join(" ")
+    }
+}
+
+/// Collects a record's key-value pairs as `key=value` strings.
+struct KeyValues(Vec<String>);
+
+impl<'kvs> VisitSource<'kvs> for KeyValues {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0
+            .push(style::edim(format!("{key}={value}")).to_string());
+        Ok(())
     }
 }
 
+/// Formats `time` as an RFC 3339 UTC timestamp with milliseconds.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3_600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
 impl Log for ProgressLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        if metadata.level() > self.level {
-            return false;
-        }
-        if let Some(ref filter) = self.target_filter {
-            metadata.target().starts_with(filter)
-        } else {
-            true
-        }
+        metadata.level() <= self.level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
@@ -110,29 +309,7 @@ impl Log for ProgressLogger {
             return;
         }
 
-        let message = self.format_message(record);
-        let was_paused = is_paused();
-
-        // Pause progress if it's running
-        // Track whether we actually paused to avoid race condition
-        let did_pause = if !was_paused && *STARTED.lock().unwrap() {
-            pause();
-            true
-        } else {
-            false
-        };
-
-        // Write the log message with terminal lock held
-        {
-            let _guard = TERM_LOCK.lock().unwrap();
-            let mut stderr = std::io::stderr().lock();
-            let _ = writeln!(stderr, "{}", message);
-        }
-
-        // Resume progress only if we paused it
-        if did_pause {
-            resume();
-        }
+        write_above(&self.format_message(record));
     }
 
     fn flush(&self) {
@@ -198,3 +375,91 @@ pub fn try_init_log_integration() -> Result<(), SetLoggerError> {
 pub fn try_init_log_integration_with_level(level: LevelFilter) -> Result<(), SetLoggerError> {
     ProgressLogger::new(level).init()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record_for<'a>(
+        level: Level,
+        target: &'a str,
+        args: std::fmt::Arguments<'a>,
+        kvs: &'a [(&'a str, &'a str)],
+        f: impl FnOnce(&Record),
+    ) {
+        f(&Record::builder()
+            .level(level)
+            .target(target)
+            .args(args)
+            .key_values(&kvs)
+            .build());
+    }
+
+    fn enabled(logger: &ProgressLogger, level: Level, target: &str) -> bool {
+        logger.enabled(&Metadata::builder().level(level).target(target).build())
+    }
+
+    #[test]
+    fn test_filter_uses_longest_matching_target() {
+        let logger =
+            ProgressLogger::new(LevelFilter::Info).filter("warn,app=debug,app::db=trace,hyper=off");
+        assert!(!enabled(&logger, Level::Info, "other"));
+        assert!(enabled(&logger, Level::Warn, "other"));
+        assert!(enabled(&logger, Level::Debug, "app::net"));
+        assert!(!enabled(&logger, Level::Trace, "app::net"));
+        assert!(enabled(&logger, Level::Trace, "app::db::pool"));
+        assert!(!enabled(&logger, Level::Error, "hyper::client"));
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_filter_bare_target_and_invalid_directives() {
+        let logger = ProgressLogger::new(LevelFilter::Error).filter("app, x=loud,,");
+        assert!(enabled(&logger, Level::Trace, "app"));
+        assert!(!enabled(&logger, Level::Warn, "x"));
+    }
+
+    #[test]
+    fn test_with_target_only_shows_matching_targets() {
+        let logger = ProgressLogger::with_target(LevelFilter::Debug, "app");
+        assert!(enabled(&logger, Level::Debug, "app::db"));
+        assert!(!enabled(&logger, Level::Error, "hyper"));
+    }
+
+    #[test]
+    fn test_format_message_parts() {
+        let logger = ProgressLogger::new(LevelFilter::Info).targets(true);
+        record_for(
+            Level::Warn,
+            "app::db",
+            format_args!("slow query"),
+            &[("ms", "250")],
+            |record| {
+                let message = logger.format_message(record);
+                assert_eq!(
+                    console::strip_ansi_codes(&message),
+                    "WARN app::db: slow query ms=250"
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_custom_formatter() {
+        let logger = ProgressLogger::new(LevelFilter::Info)
+            .format(|record| format!("<{}> {}", record.level(), record.args()));
+        record_for(Level::Info, "app", format_args!("hi"), &[], |record| {
+            assert_eq!(logger.format_message(record), "<INFO> hi");
+        });
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(format_timestamp(time), "2023-11-14T22:13:20.123Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format_timestamp(leap_day), "2000-02-29T00:00:00.000Z");
+    }
+}
@@ -103,7 +103,8 @@
 //! - `spinner(name='...')` - Animated spinner (default: `mini_dot`)
 //! - `progress_bar(flex=true)` - Progress bar that fills available width
 //! - `progress_bar(width=N)` - Fixed-width progress bar
-//! - `elapsed()` - Time since job started (e.g., "1m23s")
+//! - `elapsed()` - Time since job started (e.g., "1m23s"), frozen once it finishes
+//! - `duration()` - Time the job took, empty until it finishes
 //! - `eta()` - Estimated time remaining
 //! - `rate()` - Throughput rate (e.g., "42.5/s")
 //! - `bytes()` - Progress as human-readable bytes (e.g., "5.2 MB / 10.4 MB")
@@ -115,6 +116,34 @@
 //! - Color: `cyan`, `blue`, `green`, `yellow`, `red`, `magenta`
 //! - Style: `bold`, `dim`, `underline`
 //!
+//! [`Body`] builds the same templates from typed parts, so function and filter
+//! names come from methods rather than strings. Spinner and property names
+//! are still strings: [`try_build`](ProgressJobBuilder::try_build) fails on an
+//! unknown spinner or a property name that is not an identifier, and a
+//! property the job never sets fails when it renders:
+//!
+//! ```rust,no_run
+//! use clx::progress::{BarStyle, Body, ProgressJobBuilder};
+//!
+//! let body = Body::new().spinner().prop("message").flex_fill().bar(BarStyle::Thin);
+//! let job = ProgressJobBuilder::new()
+//!     .body(body)
+//!     .prop("message", "Downloading")
+//!     .try_start()?;
+//! # Ok::<(), clx::Error>(())
+//! ```
+//!
+//! Templates are compiled once per distinct body and shared by every job that
+//! uses it, so rendering hundreds of jobs with the same body stays cheap.
+//!
+//! ## Template Engines
+//!
+//! Bodies are rendered with Tera unless a job selects the built-in engine with
+//! [`ProgressJobBuilder::engine`]. [`TemplateEngine::Simple`] supports
+//! variables, the functions and filters above, and `{{-`/`-}}` trimming, but
+//! not `{% %}` blocks. Building clx without the default `tera` feature removes
+//! the Tera dependency and makes the built-in engine the only one.
+//!
 //! # Output Modes
 //!
 //! The progress system supports two output modes:
@@ -141,6 +170,10 @@
 //!   Each update prints a new line instead of updating in place. Useful for CI
 //!   systems and log files.
 //!
+//! - `CLX_OSC=1` or `CLX_OSC=0` - Force whether the terminal is treated as
+//!   supporting OSC progress and notifications, instead of detecting it. See
+//!   [`osc::set_support_override`](crate::osc::set_support_override).
+//!
 //! ```bash
 //! # Disable all progress display
 //! CLX_NO_PROGRESS=1 ./my-program
@@ -171,7 +204,7 @@
 //! │  ┌────────────────────────────────────────────────────────────────┐│
 //! │  │              JOBS (Mutex<Vec<Arc<ProgressJob>>>)               ││
 //! │  │  • Stores all top-level jobs                                   ││
-//! │  │  • Each job has interior mutability via Mutex                  ││
+//! │  │  • Each job has interior mutability via Mutex and atomics      ││
 //! │  └────────────────────────────────────────────────────────────────┘│
 //! │                          │                                          │
 //! │                          │ notify()                                 │
@@ -191,7 +224,7 @@
 //! │  │  2. Clone JOBS snapshot                                        ││
 //! │  │  3. Render all jobs via Tera                                   ││
 //! │  │  4. Acquire TERM_LOCK                                          ││
-//! │  │  5. Clear previous output + write new                          ││
+//! │  │  5. Rewrite the rows that changed since the last frame         ││
 //! │  │  6. Release TERM_LOCK                                          ││
 //! │  │  7. Wait on NOTIFY or timeout (INTERVAL)                       ││
 //! │  └────────────────────────────────────────────────────────────────┘│
@@ -210,14 +243,22 @@
 //! | `STOPPING` | `AtomicBool` | Signal to stop the background thread |
 //! | `INTERVAL` | `Mutex<Duration>` | Refresh interval (default 200ms) |
 //! | `NOTIFY` | `Mutex<Option<mpsc::Sender>>` | Channel to wake background thread |
+//! | `TEMPLATES` | `Mutex<Option<Templates>>` | One compiled template per distinct body and engine |
+//! | `TERM` | `RwLock<Term>` | Terminal that frames are written to |
+//! | `CAPTURE` | `Mutex<Option<Capture>>` | Saved stdout/stderr while output is captured (Unix) |
+//! | `INPUT` | `Mutex<Option<InputThread>>` | Input thread and saved terminal mode while keys are read (Unix) |
 //!
 //! ## Background Thread Lifecycle
 //!
 //! 1. **Start**: First call to `notify()` spawns the background thread via `start()`
 //! 2. **Loop**: Thread alternates between rendering and waiting for notifications
-//! 3. **Smart Refresh**: Skips terminal writes if output unchanged and no spinners animating
+//! 3. **Smart Refresh**: Skips terminal writes if output unchanged and no spinners animating,
+//!    and otherwise rewrites only the rows that differ from the previous frame
 //! 4. **Stop**: When no active jobs remain, thread exits automatically
 //!
+//! With [`set_keyboard_input`] enabled, `start()` also spawns an input thread
+//! that polls stdin and exits with the refresh thread.
+//!
 //! The background thread is lazy - it only starts when the first job update occurs,
 //! and stops automatically when all jobs complete.
 //!
@@ -225,10 +266,17 @@
 //!
 //! Job updates call `notify()` which:
 //! 1. Ensures the background thread is started
-//! 2. Sends a message on the `NOTIFY` channel
+//! 2. Sends a message on the `NOTIFY` channel, unless one is already pending
 //! 3. This wakes the background thread for immediate refresh
 //!
 //! Without notifications, the thread waits for `INTERVAL` between refreshes.
+//! Any number of updates between two frames share one notification, and the
+//! frame renders whatever state the jobs hold at that moment.
+//!
+//! Jobs updated from hot loops can also set
+//! [`ProgressJobBuilder::min_update_interval`], which skips updates that arrive
+//! sooner than the interval and marks the job dirty instead. This matters most
+//! in text mode, where every update otherwise renders the template.
 //!
 //! ## Terminal Lock Usage
 //!
@@ -240,7 +288,9 @@
 //!
 //! ## Thread Safety Guarantees
 //!
-//! - **Job updates are atomic**: Each field update acquires its own mutex
+//! - **Job updates are atomic**: Progress counters are atomics updated with
+//!   compare-and-swap, so `increment()` from many threads never blocks; other
+//!   fields each acquire their own mutex
 //! - **Display is consistent**: `REFRESH_LOCK` ensures complete render cycles
 //! - **No interleaved output**: `TERM_LOCK` serializes all terminal writes
 //! - **Safe concurrent access**: `Arc<ProgressJob>` can be shared across threads
@@ -252,6 +302,87 @@
 //! - Each `update()` call writes directly to stderr
 //! - Useful for CI/CD, piped output, or non-terminal environments
 //!
+//! ## Job Logs
+//!
+//! [`ProgressJob::log`] records a line of output for one job. While the job
+//! runs, its last few lines are shown dimmed beneath it
+//! ([`ProgressJobBuilder::log_lines`]). When it ends `Failed`, the full log is
+//! printed above the display; any other final status discards it:
+//!
+//! ```rust,no_run
+//! use clx::progress::{ProgressJobBuilder, ProgressStatus};
+//!
+//! let job = ProgressJobBuilder::new().prop("message", "cargo test").start();
+//! job.log("running 12 tests");
+//! job.log("test parse ... FAILED");
+//! job.set_status(ProgressStatus::Failed); // prints both lines
+//! ```
+//!
+//! ## Cancellation
+//!
+//! Each job carries a [`CancellationToken`] linked to its parent's.
+//! [`ProgressJob::cancel`] cancels the token, so [`ProgressJob::is_cancelled`]
+//! is `true` for the job and all of its descendants, and marks the unfinished
+//! ones [`ProgressStatus::Cancelled`]. Keyboard input and signal handling use
+//! it to stop a run.
+//!
+//! ## Job Events
+//!
+//! Each job records its [`status_history`](ProgressJob::status_history) and
+//! calls the callbacks registered with [`ProgressJob::on_status_change`],
+//! [`ProgressJob::on_progress`] and [`ProgressJob::on_done`] on the thread
+//! that made the change. [`subscribe`] returns a channel of the same changes
+//! for every job, as [`ProgressEvent`]s, with progress updates coalesced.
+//! Neither takes a lock on the [`increment`](ProgressJob::increment) path
+//! until a callback or subscriber is registered.
+//!
+//! ## Summary
+//!
+//! [`set_summary`] makes [`stop`] print a [`Summary`] of every job below the
+//! final frame: counts by final status, total elapsed time, the slowest jobs,
+//! and a line per failed or warned job. It is rendered from a template with
+//! the same engines as job bodies, in both UI and text modes.
+//!
+//! ## Timing Export
+//!
+//! [`write_chrome_trace`] and [`write_folded_stacks`] write the job tree with
+//! each job's start and end, for Perfetto or flamegraph tools. Finished jobs
+//! that have left the tree, such as `Print` jobs, are included while
+//! [`set_job_history`] is recording. Running jobs end at the time of the
+//! export.
+//!
+//! ## Panics
+//!
+//! [`install_panic_hook`] clears the frame before the previous panic hook
+//! prints its message, so the message is neither interleaved with nor
+//! overwritten by the display. A thread that called [`ProgressJob::enter`]
+//! has its job marked failed when it panics.
+//!
+//! ## Output Capture
+//!
+//! Writes that bypass clx, such as `println!` in a dependency, corrupt the
+//! live frame. On Unix, [`capture_output`] redirects fds 1 and 2 through pipes
+//! and prints each captured line above the frame, as [`ProgressJob::println`]
+//! does. Frames keep going to the original stderr. [`stop`] and
+//! [`stop_clear`] flush the remaining output and restore the descriptors.
+//!
+//! ## Keyboard Input
+//!
+//! On Unix, [`set_keyboard_input`] reads keys while the display runs. Each key
+//! goes to the handler set with [`on_key`], then to the built-in actions: `v`
+//! collapses children, `q` cancels every job, and the arrow, page, Home and End
+//! keys scroll a frame taller than the terminal. Echo and line buffering are
+//! turned off while reading and restored by [`stop`], when the refresh thread
+//! exits, and on panic.
+//!
+//! ## Signal Handling
+//!
+//! On Unix, [`handle_signals`] makes SIGINT and SIGTERM finalize the display
+//! before the process exits: running jobs are cancelled, the final frame is
+//! drawn, the cursor is shown, and the OSC progress indicator is cleared. The
+//! handler only wakes a `clx-signals` thread, which does this work and then
+//! raises the signal again or calls the callback set with [`on_signal`].
+//!
 //! ## Terminal Resize Handling
 //!
 //! On Unix systems, the progress display automatically adapts to terminal resizes:
@@ -298,6 +429,13 @@
 //! job.set_status(ProgressStatus::Done);
 //! ```
 //!
+//! ## Error Handling
+//!
+//! Errors from the background thread, [`flush`], job updates, and text mode
+//! have no caller to return to. They go to the handler set with
+//! [`set_error_handler`], which defaults to `log::error!` with the `log`
+//! feature and to stderr otherwise, and are counted by [`error_count`].
+//!
 //! ## Log Integration
 //!
 //! When the `log` feature is enabled, you can use the progress-aware logger
@@ -324,39 +462,116 @@
 //!
 //! The logger automatically pauses progress before writing and resumes afterward,
 //! preventing log output from being overwritten by progress updates.
+//!
+//! For more control, build a [`ProgressLogger`] directly. It accepts
+//! `RUST_LOG`-style per-target filters and can add timestamps, targets, and
+//! thread names, or use a custom formatter:
+//!
+//! ```rust,ignore
+//! use clx::progress::ProgressLogger;
+//! use log::LevelFilter;
+//!
+//! ProgressLogger::new(LevelFilter::Info)
+//!     .filter_env("RUST_LOG")
+//!     .timestamps(true)
+//!     .targets(true)
+//!     .init()
+//!     .unwrap();
+//! ```
+//!
+//! ## Tracing Integration
+//!
+//! The `tracing` feature provides [`ProgressLayer`], a `tracing_subscriber`
+//! layer that writes events the same way. It can also show spans as jobs:
+//!
+//! ```rust,ignore
+//! use clx::progress::ProgressLayer;
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(ProgressLayer::new().with_span_jobs(true))
+//!     .init();
+//!
+//! // Entering a span starts a job (a child job inside another span's job);
+//! // closing it marks the job done and hides it. Span fields become job
+//! // properties.
+//! let span = tracing::info_span!("build", crate_name = "clx");
+//! let _guard = span.enter();
+//! tracing::info!("compiling");
+//! ```
 
+mod atomic;
+mod body;
+mod cancel;
+#[cfg(unix)]
+mod capture;
 mod diagnostics;
+mod error_handler;
+mod events;
+mod export;
 mod flex;
 mod format;
+mod functions;
+mod history;
+#[cfg(unix)]
+mod input;
 mod job;
 mod output;
+mod panic;
 mod render;
+#[cfg(unix)]
+mod signals;
+mod simple;
 mod spinners;
 mod state;
+mod summary;
+mod template;
+#[cfg(feature = "tera")]
 mod tera_setup;
 
 #[cfg(feature = "log")]
 mod log;
+#[cfg(feature = "tracing")]
+mod tracing;
 
 // Re-export public API
+pub use body::{BarStyle, Body, IntoBody, TextStyle};
+pub use cancel::CancellationToken;
+#[cfg(unix)]
+pub use capture::{capture_output, is_capturing_output, release_output};
+pub use error_handler::{error_count, set_error_handler};
+pub use events::{ProgressEvent, subscribe};
+pub use export::{write_chrome_trace, write_folded_stacks};
+pub use history::set_job_history;
+#[cfg(unix)]
+pub use input::{Key, on_key, set_keyboard_input};
 pub use job::{ProgressJob, ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus};
 pub use output::{ProgressOutput, output, set_output};
+pub use panic::{JobGuard, install_panic_hook};
+#[cfg(unix)]
+pub use signals::{Signal, handle_signals, on_signal};
 pub use state::{
     active_jobs, clear_jobs, flush, interval, is_disabled, is_paused, job_count, pause, resume,
     set_interval, stop, stop_clear, with_terminal_lock,
 };
+pub use summary::{Summary, set_summary};
+pub use template::TemplateEngine;
+
+pub(crate) use state::{clear_osc_progress, term, with_display_paused};
 
 #[cfg(feature = "log")]
 pub use log::{
     ProgressLogger, init_log_integration, init_log_integration_with_level,
     try_init_log_integration, try_init_log_integration_with_level,
 };
+#[cfg(feature = "tracing")]
+pub use tracing::ProgressLayer;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::time::Duration;
-    use tera::Context;
+    use template::Context;
 
     // ==================== Template Helper Function Tests ====================
 
@@ -371,17 +586,23 @@ mod tests {
             tera_ctx: Context::new(),
             indent: 0,
             include_children: false,
+            collapse_children: false,
             progress,
         }
     }
 
     /// Helper to render a template with a job
     fn render_template(job: &ProgressJob, ctx: &render::RenderContext) -> String {
-        let mut tera = tera::Tera::default();
-        tera_setup::add_tera_functions(&mut tera, ctx, job);
-        tera.add_raw_template("body", &job.body.lock().unwrap())
-            .unwrap();
-        tera.render("body", &ctx.tera_ctx).unwrap()
+        let mut ctx = ctx.clone();
+        let state = functions::RenderState::new(&ctx, job);
+        template::Templates::new()
+            .render(
+                job.engine,
+                &job.body.lock().unwrap(),
+                &mut ctx.tera_ctx,
+                &state,
+            )
+            .unwrap()
     }
 
     #[test]
@@ -392,6 +613,20 @@ mod tests {
         assert_eq!(result, "0s");
     }
 
+    #[test]
+    fn test_template_elapsed_freezes_when_finished() {
+        let mut job = ProgressJobBuilder::new()
+            .body("{{ elapsed() }}|{{ duration() }}")
+            .build();
+        job.start -= Duration::from_secs(90);
+        let ctx = test_render_context(None);
+        assert_eq!(render_template(&job, &ctx), "1m30s|");
+
+        job.set_status(ProgressStatus::Done);
+        *job.finished.lock().unwrap() = Some(job.start + Duration::from_secs(60));
+        assert_eq!(render_template(&job, &ctx), "1m0s|1m0s");
+    }
+
     #[test]
     fn test_template_eta_no_progress() {
         let job = ProgressJobBuilder::new().body("{{ eta() }}").build();
@@ -443,7 +678,7 @@ mod tests {
             .progress_current(100)
             .progress_total(200)
             .build();
-        *job.smoothed_rate.lock().unwrap() = Some(10.0);
+        job.smoothed_rate.store(Some(10.0));
         let ctx = test_render_context(Some((100, 200)));
         let result = render_template(&job, &ctx);
         assert_eq!(result, "10.0/s");
@@ -456,7 +691,7 @@ mod tests {
             .progress_current(1)
             .progress_total(100)
             .build();
-        *job.smoothed_rate.lock().unwrap() = Some(0.5);
+        job.smoothed_rate.store(Some(0.5));
         let ctx = test_render_context(Some((1, 100)));
         let result = render_template(&job, &ctx);
         assert_eq!(result, "30.0/m");
@@ -469,7 +704,7 @@ mod tests {
             .progress_current(1)
             .progress_total(100)
             .build();
-        *job.smoothed_rate.lock().unwrap() = Some(0.01);
+        job.smoothed_rate.store(Some(0.01));
         let ctx = test_render_context(Some((1, 100)));
         let result = render_template(&job, &ctx);
         assert_eq!(result, "0.01/s");
@@ -638,18 +873,41 @@ mod tests {
         assert_eq!(result, "");
     }
 
+    #[cfg(feature = "tera")]
+    #[test]
+    fn test_simple_engine_matches_tera() {
+        let body = "{{ spinner() }} {{ message | bold }} {{ percentage(decimals=1) }} \
+                    {{ bytes(total=false) }} {{ progress_bar(width=12, style='thin') }} \
+                    {{ 'done' | flex_fill }}";
+        let render_with = |engine| {
+            let job = ProgressJobBuilder::new()
+                .engine(engine)
+                .body(body)
+                .prop("message", "Building")
+                .status(ProgressStatus::Done)
+                .build();
+            let mut ctx = test_render_context(Some((3, 8)));
+            ctx.tera_ctx.insert("message", "Building");
+            render_template(&job, &ctx)
+        };
+        assert_eq!(
+            render_with(TemplateEngine::Simple),
+            render_with(TemplateEngine::Tera)
+        );
+    }
+
     // ==================== ETA/Rate Smoothing Tests ====================
 
     #[test]
     fn test_smoothed_rate_initial_value() {
         let job = ProgressJobBuilder::new().progress_total(100).build();
-        assert!(job.smoothed_rate.lock().unwrap().is_none());
+        assert!(job.smoothed_rate.load().is_none());
 
         job.progress_current(10);
         std::thread::sleep(Duration::from_millis(150));
         job.progress_current(20);
 
-        let rate = job.smoothed_rate.lock().unwrap();
+        let rate = job.smoothed_rate.load();
         assert!(rate.is_some(), "Expected smoothed rate after second update");
         let rate_value = rate.unwrap();
         assert!(
@@ -669,16 +927,16 @@ mod tests {
         job.progress_current(100);
         std::thread::sleep(Duration::from_millis(150));
 
-        let rate1 = job.smoothed_rate.lock().unwrap().unwrap();
+        let rate1 = job.smoothed_rate.load().unwrap();
 
         job.progress_current(200);
         std::thread::sleep(Duration::from_millis(150));
 
-        let rate2 = job.smoothed_rate.lock().unwrap().unwrap();
+        let rate2 = job.smoothed_rate.load().unwrap();
 
         job.progress_current(300);
 
-        let rate3 = job.smoothed_rate.lock().unwrap().unwrap();
+        let rate3 = job.smoothed_rate.load().unwrap();
 
         assert!(rate1 > 0.0);
         assert!(rate2 > 0.0);
@@ -693,7 +951,7 @@ mod tests {
         std::thread::sleep(Duration::from_millis(150));
 
         job.progress_current(50);
-        let rate_after_forward = *job.smoothed_rate.lock().unwrap();
+        let rate_after_forward = job.smoothed_rate.load();
 
         assert!(
             rate_after_forward.is_some(),
@@ -703,7 +961,7 @@ mod tests {
         std::thread::sleep(Duration::from_millis(150));
 
         job.progress_current(30);
-        let rate_after_attempt = *job.smoothed_rate.lock().unwrap();
+        let rate_after_attempt = job.smoothed_rate.load();
 
         assert_eq!(rate_after_forward, rate_after_attempt);
     }
@@ -716,21 +974,21 @@ mod tests {
         job.progress_current(10);
         job.progress_current(20);
 
-        let _rate = job.smoothed_rate.lock().unwrap();
+        let _rate = job.smoothed_rate.load();
     }
 
     #[test]
     fn test_increment_updates_smoothed_rate() {
         let job = ProgressJobBuilder::new().progress_total(100).build();
 
-        assert!(job.smoothed_rate.lock().unwrap().is_none());
+        assert!(job.smoothed_rate.load().is_none());
 
         job.increment(10);
         std::thread::sleep(Duration::from_millis(150));
 
         job.increment(10);
 
-        let rate = job.smoothed_rate.lock().unwrap();
+        let rate = job.smoothed_rate.load();
         assert!(rate.is_some(), "Expected smoothed rate after increments");
     }
 
@@ -742,7 +1000,7 @@ mod tests {
             .progress_total(100)
             .build();
 
-        *job.smoothed_rate.lock().unwrap() = Some(10.0);
+        job.smoothed_rate.store(Some(10.0));
 
         let ctx = test_render_context(Some((50, 100)));
         let result = render_template(&job, &ctx);
@@ -758,7 +1016,7 @@ mod tests {
             .progress_total(100)
             .build();
 
-        *job.smoothed_rate.lock().unwrap() = Some(42.5);
+        job.smoothed_rate.store(Some(42.5));
 
         let ctx = test_render_context(Some((50, 100)));
         let result = render_template(&job, &ctx);
@@ -774,7 +1032,7 @@ mod tests {
             .progress_total(100)
             .build();
 
-        assert!(job.smoothed_rate.lock().unwrap().is_none());
+        assert!(job.smoothed_rate.load().is_none());
 
         let ctx = test_render_context(Some((50, 100)));
         let result = render_template(&job, &ctx);
@@ -794,7 +1052,7 @@ mod tests {
             .progress_total(200)
             .build();
 
-        assert!(job.smoothed_rate.lock().unwrap().is_none());
+        assert!(job.smoothed_rate.load().is_none());
 
         let ctx = test_render_context(Some((100, 200)));
         let result = render_template(&job, &ctx);
@@ -814,7 +1072,7 @@ mod tests {
             .progress_total(100)
             .build();
 
-        *job.smoothed_rate.lock().unwrap() = Some(0.0);
+        job.smoothed_rate.store(Some(0.0));
 
         let ctx = test_render_context(Some((50, 100)));
         let result = render_template(&job, &ctx);
@@ -834,7 +1092,7 @@ mod tests {
             .progress_total(100)
             .build();
 
-        *job.smoothed_rate.lock().unwrap() = Some(0.0);
+        job.smoothed_rate.store(Some(0.0));
 
         let ctx = test_render_context(Some((50, 100)));
         let result = render_template(&job, &ctx);
@@ -878,6 +1136,8 @@ mod tests {
     #[test]
     fn test_clear_jobs_rearms_rendering_after_stop() {
         use std::sync::atomic::Ordering;
+        // stop() clears the OSC progress indicator.
+        let _lock = crate::osc::test_lock();
         // Drive into the "stopped" state and confirm STOPPING is latched.
         let job = ProgressJobBuilder::new().prop("message", "a").start();
         job.set_status(ProgressStatus::Done);
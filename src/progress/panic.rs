@@ -0,0 +1,206 @@
+//! Panic hook that keeps panic messages clear of the progress display.
+//!
+//! A panic message written while the frame is on screen lands in the middle
+//! of it and is partly overwritten by the next redraw. The hook installed by
+//! [`install_panic_hook`] clears the frame first, lets the previous hook print
+//! the message, and leaves the refresh thread to redraw the frame below it.
+//!
+//! The panicking thread may itself hold one of the display locks, for example
+//! inside [`with_terminal_lock`](super::with_terminal_lock), so the hook only
+//! waits a short while for each and prints without clearing if it cannot
+//! get them.
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Once, TryLockError, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::job::{ProgressJob, ProgressStatus};
+use super::state::{
+    LAST_OUTPUT, LINES, REFRESH_LOCK, TERM_LOCK, notify, renders_to_terminal, term,
+};
+
+/// How long the hook waits for each display lock before printing anyway.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long the hook waits for a failed job's display update.
+const FINISH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Whether [`install_panic_hook`] has been called.
+static CLEAR_ON_PANIC: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Jobs entered on this thread with [`ProgressJob::enter`], innermost last.
+    static ENTERED_JOBS: RefCell<Vec<Arc<ProgressJob>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks a job as the one the current thread is working on until dropped.
+///
+/// Returned by [`ProgressJob::enter`].
+#[must_use = "the job is only entered until the guard is dropped"]
+#[derive(Debug)]
+pub struct JobGuard {
+    // Entered jobs are tracked per thread, so the guard must stay on it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let _ = ENTERED_JOBS.try_with(|jobs| jobs.borrow_mut().pop());
+    }
+}
+
+pub(crate) fn enter(job: Arc<ProgressJob>) -> JobGuard {
+    ENTERED_JOBS.with(|jobs| jobs.borrow_mut().push(job));
+    JobGuard {
+        _not_send: PhantomData,
+    }
+}
+
+/// Installs a panic hook that prints panic messages cleanly above the
+/// progress display.
+///
+/// When any thread panics, the live frame is cleared and the previously
+/// installed hook (by default, the one that prints the message) runs with the
+/// terminal lock held. If the panicking thread has entered a job with
+/// [`ProgressJob::enter`], that job is marked
+/// [`Failed`](ProgressStatus::Failed) as by
+/// [`set_status`](ProgressJob::set_status), so its log is printed too. Its
+/// status callbacks run on a separate thread, since the panicking thread may
+/// hold the display locks. The refresh thread redraws the frame below the
+/// message.
+///
+/// Calling this more than once does nothing. The hook also restores the
+/// terminal mode changed by [`set_keyboard_input`](super::set_keyboard_input),
+/// which installs it without the clearing and job failing described here.
+///
+/// ```rust,no_run
+/// use clx::progress::{ProgressJobBuilder, install_panic_hook};
+///
+/// install_panic_hook();
+/// let job = ProgressJobBuilder::new().prop("message", "Working").start();
+/// std::thread::spawn(move || {
+///     let _entered = job.enter();
+///     panic!("worker failed"); // printed above the frame; the job shows as failed
+/// });
+/// ```
+pub fn install_panic_hook() {
+    CLEAR_ON_PANIC.store(true, Ordering::Relaxed);
+    install_hook();
+}
+
+/// Installs the crate's panic hook, once. It clears the frame and fails the
+/// entered job only after [`install_panic_hook`].
+pub(crate) fn install_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            #[cfg(unix)]
+            super::input::stop_input();
+            if CLEAR_ON_PANIC.load(Ordering::Relaxed) {
+                fail_entered_job();
+                write_cleared(|| previous(info));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+fn fail_entered_job() {
+    let job = ENTERED_JOBS
+        .try_with(|jobs| jobs.try_borrow().ok().and_then(|jobs| jobs.last().cloned()))
+        .ok()
+        .flatten();
+    if let Some(job) = job
+        && let Some(mut status) = lock_within_timeout(&job.status)
+        && (status.is_active() || *status == ProgressStatus::Pending)
+    {
+        let old = std::mem::replace(&mut *status, ProgressStatus::Failed);
+        drop(status);
+        // The rest of the change takes the display locks, which this thread
+        // may hold if it panicked while writing. It runs on another thread,
+        // which the hook waits for only a short while; if this thread holds a
+        // lock, the change completes once unwinding releases it.
+        let (done, finished) = mpsc::channel();
+        let job = job.clone();
+        let spawned = thread::Builder::new()
+            .name("clx-panic".into())
+            .spawn(move || {
+                job.status_changed(&old, &ProgressStatus::Failed);
+                let _ = done.send(());
+            });
+        if spawned.is_ok() {
+            let _ = finished.recv_timeout(FINISH_TIMEOUT);
+        }
+    }
+}
+
+/// Clears the frame and runs `write` with the terminal lock held, or just
+/// runs `write` if the locks cannot be taken in time.
+fn write_cleared(write: impl FnOnce()) {
+    let Some(refresh_guard) = lock_within_timeout(&REFRESH_LOCK) else {
+        return write();
+    };
+    let Some(mut lines) = lock_within_timeout(&LINES) else {
+        return write();
+    };
+    let Some(term_guard) = lock_within_timeout(&TERM_LOCK) else {
+        return write();
+    };
+    let cleared = renders_to_terminal() && *lines > 0;
+    if cleared {
+        let term = term();
+        let _ = term.move_cursor_up(*lines);
+        let _ = term.move_cursor_left(term.size().1 as usize);
+        let _ = term.clear_to_end_of_screen();
+        let _ = term.show_cursor();
+    }
+    *lines = 0;
+    write();
+    drop(term_guard);
+    drop(lines);
+    drop(refresh_guard);
+    if cleared {
+        // Force a full redraw below the message.
+        if let Some(mut last_output) = lock_within_timeout(&LAST_OUTPUT) {
+            last_output.clear();
+        }
+        notify();
+    }
+}
+
+/// Locks `mutex`, giving up after [`LOCK_TIMEOUT`]. A poisoned lock is still
+/// used, since the hook may run while another panic is unwinding.
+fn lock_within_timeout<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(TryLockError::Poisoned(err)) => return Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(TryLockError::WouldBlock) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressJobBuilder;
+
+    #[test]
+    fn test_job_guard_pops_entered_job() {
+        let job = Arc::new(ProgressJobBuilder::new().build());
+        {
+            let _entered = job.enter();
+            assert_eq!(ENTERED_JOBS.with(|jobs| jobs.borrow().len()), 1);
+        }
+        assert_eq!(ENTERED_JOBS.with(|jobs| jobs.borrow().len()), 0);
+    }
+}
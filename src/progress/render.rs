@@ -3,18 +3,18 @@
 use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{Duration, Instant};
 
-use tera::{Context, Tera};
-
 use crate::Result;
 
 use super::diagnostics;
 use super::flex::flex;
-use super::job::ProgressJob;
+use super::job::{ProgressJob, run_render_error_handlers};
 use super::output::{ProgressOutput, output};
 use super::state::{
     CRAMPED_VIEWPORT, JOBS, LAST_OUTPUT, LINES, REFRESH_LOCK, RENDER_CTX, STARTED, STOPPING,
-    SyncUpdate, TERA, TERM_LOCK, is_disabled, is_paused, term, update_osc_progress,
+    SyncUpdate, TEMPLATES, TERM_LOCK, is_disabled, is_paused, is_prompting, term,
+    update_osc_progress,
 };
+use super::template::{Context, Templates};
 
 const RESIZE_SETTLE_TIME: Duration = Duration::from_millis(100);
 
@@ -83,6 +83,8 @@ pub struct RenderContext {
     pub tera_ctx: Context,
     pub indent: usize,
     pub include_children: bool,
+    /// Leaves out children while still showing job logs, for `v`.
+    pub collapse_children: bool,
     pub progress: Option<(usize, usize)>,
 }
 
@@ -97,6 +99,7 @@ impl Default for RenderContext {
             tera_ctx,
             indent: 0,
             include_children: true,
+            collapse_children: false,
             progress: None,
         }
     }
@@ -124,26 +127,38 @@ pub(crate) struct RenderedFrame {
     pub jobs: Vec<Arc<ProgressJob>>,
 }
 
-/// Prepares the Tera engine and renders all jobs to a string.
+/// Prepares the template cache and renders all jobs to a string.
 pub(crate) fn render_frame() -> Result<RenderedFrame> {
     let ctx = prepare_render_context();
-    let mut tera = TERA.lock().unwrap();
-    if tera.is_none() {
-        *tera = Some(Tera::default());
-    }
-    let tera = tera.as_mut().unwrap();
     let jobs = JOBS.lock().unwrap().clone();
-
+    // Before taking the template cache, which the title template also uses.
     update_osc_progress(&jobs);
+    // `v` hides the children of top-level jobs while keys are being read.
+    // The refresh thread draws the final frame itself once no top-level job
+    // is running, so that frame shows the whole tree.
+    #[cfg(unix)]
+    let ctx = RenderContext {
+        collapse_children: super::input::children_collapsed()
+            && jobs.iter().any(|job| job.is_running()),
+        ..ctx
+    };
+    let mut templates = TEMPLATES.lock().unwrap();
+    let templates = templates.get_or_insert_with(Templates::new);
 
     let output = jobs
         .iter()
-        .map(|job| job.render(tera, ctx.clone()))
+        .map(|job| job.render(templates, ctx.clone()))
         .collect::<Result<Vec<_>>>()?
         .into_iter()
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>()
         .join("\n");
+    #[cfg(unix)]
+    let output = if super::input::is_reading() {
+        super::input::scroll_frame(output, term().size().0 as usize)
+    } else {
+        output
+    };
 
     Ok(RenderedFrame { output, jobs })
 }
@@ -214,6 +229,21 @@ pub(crate) fn write_frame(output: &str, jobs: &[Arc<ProgressJob>]) -> Result<boo
 
     CRAMPED_VIEWPORT.store(false, std::sync::atomic::Ordering::Relaxed);
     let _sync = SyncUpdate::begin();
+    // Rewrite only the rows that changed when the frame on screen is known
+    // row-for-row: nothing was cleared or reflowed since it was written and no
+    // line of either frame wraps.
+    if resize_action == ResizeAction::None
+        && !output.is_empty()
+        && *lines == previous_output.lines().count()
+        && previous_height == *lines
+        && output_height == output.lines().count()
+    {
+        diagnostics::log_frame(output, jobs);
+        term.hide_cursor()?;
+        term.write_str(&diff_frame(&previous_output, output))?;
+        *lines = output_height;
+        return Ok(true);
+    }
     if resize_action == ResizeAction::ClearAndRender {
         // A terminal can reflow the old frame before clx observes its new
         // dimensions, moving some of that frame into inaccessible scrollback.
@@ -241,6 +271,48 @@ pub(crate) fn write_frame(output: &str, jobs: &[Arc<ProgressJob>]) -> Result<boo
     Ok(true)
 }
 
+/// Builds the escapes that turn the `previous` frame into `output`, assuming
+/// the cursor sits on the row just below `previous` and neither frame wraps.
+///
+/// Rows before the first difference are left alone, unchanged rows after it are
+/// skipped with a cursor move, and rows left over from a taller previous frame
+/// are cleared. The cursor ends on the row just below `output`.
+pub(crate) fn diff_frame(previous: &str, output: &str) -> String {
+    let old = previous.lines().collect::<Vec<_>>();
+    let new = output.lines().collect::<Vec<_>>();
+    let Some(first_changed) = (0..old.len().max(new.len())).find(|&i| old.get(i) != new.get(i))
+    else {
+        return String::new();
+    };
+
+    let mut out = String::new();
+    let up = old.len() - first_changed;
+    if up > 0 {
+        out.push_str(&format!("\x1b[{up}A"));
+    }
+    let mut skipped = 0;
+    for (i, line) in new.iter().enumerate().skip(first_changed) {
+        if old.get(i) == Some(line) {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            out.push_str(&format!("\x1b[{skipped}B"));
+            skipped = 0;
+        }
+        out.push_str("\r\x1b[2K");
+        out.push_str(line);
+        out.push('\n');
+    }
+    if skipped > 0 {
+        out.push_str(&format!("\x1b[{skipped}B"));
+    }
+    if old.len() > new.len() {
+        out.push_str("\r\x1b[0J");
+    }
+    out
+}
+
 pub(crate) fn rendered_height(output: &str, width: usize) -> usize {
     output
         .lines()
@@ -284,7 +356,7 @@ pub fn refresh() -> Result<bool> {
         *STARTED.lock().unwrap() = false;
         return Ok(false);
     }
-    if is_paused() {
+    if is_paused() || is_prompting() {
         return Ok(true);
     }
 
@@ -329,8 +401,12 @@ pub fn refresh_once() -> Result<()> {
     if is_disabled() || matches!(output(), ProgressOutput::Quiet | ProgressOutput::Text) {
         return Ok(());
     }
-    let _refresh_guard = REFRESH_LOCK.lock().unwrap();
-    refresh_once_locked()
+    let refreshed = {
+        let _refresh_guard = REFRESH_LOCK.lock().unwrap();
+        refresh_once_locked()
+    };
+    run_render_error_handlers();
+    refreshed
 }
 
 pub(crate) fn refresh_once_locked() -> Result<()> {
@@ -341,6 +417,10 @@ pub(crate) fn refresh_once_locked() -> Result<()> {
     if !*STARTED.lock().unwrap() {
         return Ok(());
     }
+    // A prompt has the terminal; the next refresh after it closes redraws.
+    if is_prompting() {
+        return Ok(());
+    }
 
     let frame = render_frame()?;
     let final_output = process_flex_output(&frame.output);
@@ -425,12 +505,6 @@ pub fn indent(s: String, width: usize, indent_size: usize) -> String {
     result.join("\n")
 }
 
-/// Adds a raw template to the Tera engine, updating it if it already exists.
-pub fn add_tera_template(tera: &mut Tera, name: &str, body: &str) -> Result<()> {
-    tera.add_raw_template(name, body)?;
-    Ok(())
-}
-
 /// Helper to render for text mode output.
 pub fn render_text_mode(job: &ProgressJob) -> Result<()> {
     let mut ctx = RenderContext {
@@ -438,12 +512,9 @@ pub fn render_text_mode(job: &ProgressJob) -> Result<()> {
         ..Default::default()
     };
     ctx.tera_ctx.insert("message", "");
-    let mut tera = TERA.lock().unwrap();
-    if tera.is_none() {
-        *tera = Some(Tera::default());
-    }
-    let tera = tera.as_mut().unwrap();
-    let output = job.render(tera, ctx)?;
+    let mut templates = TEMPLATES.lock().unwrap();
+    let templates = templates.get_or_insert_with(Templates::new);
+    let output = job.render(templates, ctx)?;
     if !output.is_empty() {
         // Safety check: ensure no flex tags are visible
         let final_output = if output.contains("<clx:flex>") {
@@ -504,6 +575,26 @@ mod tests {
         assert_eq!(rendered_height(&output, 20), 1);
     }
 
+    #[test]
+    fn diff_frame_rewrites_only_changed_rows() {
+        assert_eq!(diff_frame("a\nb\nc", "a\nb\nc"), "");
+        assert_eq!(
+            diff_frame("a\nb\nc", "a\nB\nc"),
+            "\x1b[2A\r\x1b[2KB\n\x1b[1B"
+        );
+        assert_eq!(
+            diff_frame("a\nb\nc", "A\nb\nC"),
+            "\x1b[3A\r\x1b[2KA\n\x1b[1B\r\x1b[2KC\n"
+        );
+    }
+
+    #[test]
+    fn diff_frame_handles_growing_and_shrinking_frames() {
+        assert_eq!(diff_frame("a", "a\nb"), "\r\x1b[2Kb\n");
+        assert_eq!(diff_frame("a\nb\nc", "a"), "\x1b[2A\r\x1b[0J");
+        assert_eq!(diff_frame("a\nb\nc", "x"), "\x1b[3A\r\x1b[2Kx\n\r\x1b[0J");
+    }
+
     #[test]
     fn deferred_frame_does_not_advance_output_cache() {
         let mut last_output = "visible frame".to_string();
@@ -564,4 +655,25 @@ mod tests {
             ResizeAction::ClearAndRender
         );
     }
+
+    #[test]
+    fn children_do_not_inherit_parent_progress() {
+        let parent = crate::progress::ProgressJobBuilder::new()
+            .body("{{ percentage() }}")
+            .progress_total(4)
+            .progress_current(1)
+            .build();
+        let child = crate::progress::ProgressJobBuilder::new()
+            .body("[{{ percentage() }}]")
+            .build();
+        parent.children.lock().unwrap().push(Arc::new(child));
+        let ctx = RenderContext {
+            width: 80,
+            ..Default::default()
+        };
+
+        let output = parent.render(&mut Templates::new(), ctx).unwrap();
+
+        assert_eq!(output, "25%\n []");
+    }
 }
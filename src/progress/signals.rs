@@ -0,0 +1,181 @@
+//! Graceful handling of SIGINT and SIGTERM.
+//!
+//! By default, Ctrl-C kills the process mid-frame: the cursor stays hidden,
+//! the frame shows jobs that will never finish, and the terminal's OSC
+//! progress indicator stays set. [`handle_signals`] installs handlers that
+//! finalize the display first.
+//!
+//! Signal handlers may only do async-signal-safe work, so the handler just
+//! writes the signal number to a pipe. A `clx-signals` thread reads it and
+//! does the cleanup on an ordinary thread, where taking locks is allowed.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::{BorrowedFd, IntoRawFd};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+
+use nix::errno::Errno;
+use nix::sys::signal::{
+    self as nix_signal, SaFlags, SigAction, SigHandler, SigSet, raise, sigaction,
+};
+
+use crate::Result;
+
+use super::state::{JOBS, stop};
+
+type SignalCallback = Arc<dyn Fn(Signal) + Send + Sync>;
+
+/// A signal handled by [`handle_signals`], passed to the [`on_signal`]
+/// callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Signal {
+    /// SIGINT, sent by Ctrl-C.
+    Interrupt,
+    /// SIGTERM.
+    Terminate,
+}
+
+impl Signal {
+    /// Returns the signal number, e.g. for an exit code of `128 + number`.
+    #[must_use]
+    pub fn number(self) -> i32 {
+        self.to_nix() as i32
+    }
+
+    fn to_nix(self) -> nix_signal::Signal {
+        match self {
+            Signal::Interrupt => nix_signal::Signal::SIGINT,
+            Signal::Terminate => nix_signal::Signal::SIGTERM,
+        }
+    }
+
+    fn from_number(number: i32) -> Option<Self> {
+        [Signal::Interrupt, Signal::Terminate]
+            .into_iter()
+            .find(|signal| signal.number() == number)
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_nix().as_str())
+    }
+}
+
+/// Write end of the pipe the signal handler wakes the signal thread with.
+static WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+static INSTALLED: Mutex<bool> = Mutex::new(false);
+static CALLBACK: RwLock<Option<SignalCallback>> = RwLock::new(None);
+
+/// Finalizes the progress display when the process receives SIGINT or SIGTERM.
+///
+/// On either signal, every job is cancelled as by
+/// [`ProgressJob::cancel`](super::ProgressJob::cancel) and the display is
+/// stopped as by [`stop`](super::stop): the final frame is drawn, the cursor
+/// is shown again, the OSC progress indicator is cleared, and keyboard input
+/// and output capture are released. The signal is then raised
+/// again with its default action, so the process exits as it would have, unless
+/// a callback set with [`on_signal`] handles it instead.
+///
+/// Handlers are opt-in because they replace any the application installed
+/// for these signals. Calling this more than once does nothing.
+///
+/// # Errors
+///
+/// Returns an error if the wake-up pipe, the signal thread, or the handlers
+/// cannot be created.
+///
+/// ```rust,no_run
+/// use clx::progress::{ProgressJobBuilder, handle_signals};
+///
+/// handle_signals().unwrap();
+/// let job = ProgressJobBuilder::new().prop("message", "Press Ctrl-C").start();
+/// ```
+pub fn handle_signals() -> Result<()> {
+    let mut installed = INSTALLED.lock().unwrap();
+    if *installed {
+        return Ok(());
+    }
+    let (reader, writer) = nix::unistd::pipe()?;
+    thread::Builder::new()
+        .name("clx-signals".into())
+        .spawn(move || wait_for_signals(File::from(reader)))?;
+    // The write end stays open for the life of the process.
+    WAKE_FD.store(writer.into_raw_fd(), Ordering::Relaxed);
+
+    let action = SigAction::new(
+        SigHandler::Handler(handle_signal),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    for signal in [Signal::Interrupt, Signal::Terminate] {
+        // SAFETY: the handler only calls write(2), which is async-signal-safe.
+        unsafe { sigaction(signal.to_nix(), &action) }?;
+    }
+    *installed = true;
+    Ok(())
+}
+
+/// Sets a callback run after the display is finalized for SIGINT or SIGTERM,
+/// instead of raising the signal again.
+///
+/// The callback runs on the signal thread and decides how the program ends,
+/// for example by exiting with a particular code. If it returns, the program
+/// keeps running and later signals are handled the same way. Has no effect
+/// unless [`handle_signals`] is called. Replaces any previous callback.
+///
+/// ```rust,no_run
+/// use clx::progress::{handle_signals, on_signal};
+///
+/// handle_signals().unwrap();
+/// on_signal(|signal| {
+///     eprintln!("interrupted by {signal}");
+///     std::process::exit(128 + signal.number());
+/// });
+/// ```
+pub fn on_signal<F>(callback: F)
+where
+    F: Fn(Signal) + Send + Sync + 'static,
+{
+    *CALLBACK.write().unwrap() = Some(Arc::new(callback));
+}
+
+extern "C" fn handle_signal(signal: nix::libc::c_int) {
+    // The interrupted code may be about to read errno.
+    let errno = Errno::last_raw();
+    let fd = WAKE_FD.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // SAFETY: the descriptor is never closed once stored.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        let _ = nix::unistd::write(fd, &[signal as u8]);
+    }
+    Errno::set_raw(errno);
+}
+
+fn wait_for_signals(mut reader: File) {
+    let mut byte = [0; 1];
+    while reader.read_exact(&mut byte).is_ok() {
+        let Some(signal) = Signal::from_number(i32::from(byte[0])) else {
+            continue;
+        };
+        for job in JOBS.lock().unwrap().clone() {
+            // The final frame drawn by `stop` shows every cancelled job.
+            job.mark_cancelled();
+        }
+        stop();
+
+        let callback = CALLBACK.read().unwrap().clone();
+        match callback {
+            Some(callback) => callback(signal),
+            None => {
+                // SAFETY: restoring the default disposition installs no handler.
+                let _ = unsafe { nix_signal::signal(signal.to_nix(), SigHandler::SigDfl) };
+                let _ = raise(signal.to_nix());
+            }
+        }
+    }
+}
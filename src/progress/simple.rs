@@ -0,0 +1,571 @@
+//! Built-in lightweight template engine.
+//!
+//! Supports the subset of Tera syntax that progress bodies typically use:
+//! `{{ variable }}`, `{{ function(key=value) }}`, string literals, and
+//! `| filter(key=value)` chains, with `{{-`/`-}}` whitespace trimming. Only
+//! the clx functions and filters are available, and `{% %}` blocks are
+//! rejected. Jobs select it with [`TemplateEngine::Simple`], and it is the
+//! only engine when clx is built without the `tera` feature.
+//!
+//! [`TemplateEngine::Simple`]: super::TemplateEngine::Simple
+
+use crate::{Error, Result};
+
+use super::functions::{self, ProgressBarArgs, RenderState};
+use super::template::Context;
+
+const FUNCTIONS: &[&str] = &[
+    "spinner",
+    "progress_bar",
+    "elapsed",
+    "duration",
+    "eta",
+    "rate",
+    "bytes",
+    "percentage",
+    "count_format",
+];
+
+const FILTERS: &[&str] = &["flex", "flex_fill", "truncate_text"];
+
+/// A parsed template body.
+#[derive(Debug)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+struct Expr {
+    term: Term,
+    filters: Vec<Call>,
+}
+
+#[derive(Debug)]
+enum Term {
+    Var(String),
+    Call(Call),
+    Literal(Literal),
+}
+
+#[derive(Debug)]
+struct Call {
+    name: String,
+    args: Vec<(String, Literal)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl Literal {
+    fn to_output(&self) -> String {
+        match self {
+            Literal::Str(s) => s.clone(),
+            Literal::Int(n) => n.to_string(),
+            Literal::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+fn template_error(msg: impl Into<String>) -> Error {
+    Error::Template(msg.into())
+}
+
+impl Template {
+    /// Parses a template body.
+    pub fn parse(body: &str) -> Result<Self> {
+        let mut segments = vec![];
+        let mut rest = body;
+        let mut trim_next = false;
+        while let Some(open) = rest.find('{') {
+            let tag = rest.get(open..open + 2);
+            if matches!(tag, Some("{%") | Some("{#")) {
+                return Err(template_error(format!(
+                    "`{}` blocks are not supported by the simple template engine",
+                    tag.unwrap()
+                )));
+            }
+            if tag != Some("{{") {
+                // A lone brace is plain text.
+                push_text(&mut segments, &rest[..=open], &mut trim_next, false);
+                rest = &rest[open + 1..];
+                continue;
+            }
+            let inner_start = open + 2;
+            let trim_prev = rest[inner_start..].starts_with('-');
+            push_text(&mut segments, &rest[..open], &mut trim_next, trim_prev);
+            let close = find_close(&rest[inner_start..])
+                .ok_or_else(|| template_error("unclosed `{{` in template"))?;
+            let mut inner = &rest[inner_start..inner_start + close];
+            if trim_prev {
+                inner = &inner[1..];
+            }
+            if let Some(stripped) = inner.strip_suffix('-') {
+                inner = stripped;
+                trim_next = true;
+            }
+            segments.push(Segment::Expr(Parser::new(inner)?.expr()?));
+            rest = &rest[inner_start + close + 2..];
+        }
+        push_text(&mut segments, rest, &mut trim_next, false);
+        Ok(Self { segments })
+    }
+
+    /// Renders the template with the job's properties and render state.
+    pub fn render(&self, ctx: &Context, state: &RenderState) -> Result<String> {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Expr(expr) => out.push_str(&expr.eval(ctx, state)?),
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn push_text(segments: &mut Vec<Segment>, text: &str, trim_start: &mut bool, trim_end: bool) {
+    let mut text = text;
+    if std::mem::take(trim_start) {
+        text = text.trim_start();
+    }
+    if trim_end {
+        text = text.trim_end();
+    }
+    if text.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(Segment::Text(prev)) => prev.push_str(text),
+        _ => segments.push(Segment::Text(text.to_string())),
+    }
+}
+
+/// Finds the `}}` closing an expression, skipping over string literals.
+fn find_close(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if matches!(c, '"' | '\'' | '`') => quote = Some(c),
+            None if s[i..].starts_with("}}") => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Literal),
+    LParen,
+    RParen,
+    Comma,
+    Eq,
+    Pipe,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = src.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => Token::Eq,
+            '|' => Token::Pipe,
+            '"' | '\'' | '`' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, q)) if q == c => break,
+                        Some((_, ch)) => s.push(ch),
+                        None => return Err(template_error("unterminated string literal")),
+                    }
+                }
+                Token::Literal(Literal::Str(s))
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, ch)) = chars.peek() {
+                    if !ch.is_ascii_digit() {
+                        break;
+                    }
+                    end = i + ch.len_utf8();
+                    chars.next();
+                }
+                let n = src[start..end].parse().map_err(|_| {
+                    template_error(format!("invalid number `{}`", &src[start..end]))
+                })?;
+                Token::Literal(Literal::Int(n))
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, ch)) = chars.peek() {
+                    if !(ch.is_alphanumeric() || ch == '_') {
+                        break;
+                    }
+                    end = i + ch.len_utf8();
+                    chars.next();
+                }
+                match &src[start..end] {
+                    "true" => Token::Literal(Literal::Bool(true)),
+                    "false" => Token::Literal(Literal::Bool(false)),
+                    ident => Token::Ident(ident.to_string()),
+                }
+            }
+            c => return Err(template_error(format!("unexpected character `{c}`"))),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Parser {
+    fn new(src: &str) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(src)?.into_iter(),
+            peeked: None,
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(template_error(format!(
+                "expected {expected:?}, found {other:?}"
+            ))),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let term = match self.next() {
+            Some(Token::Literal(literal)) => Term::Literal(literal),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                if !FUNCTIONS.contains(&name.as_str()) {
+                    return Err(template_error(format!("unknown function `{name}`")));
+                }
+                let call = self.call(name)?;
+                if call.name == "spinner"
+                    && let Some(name) = call.str("name")?
+                {
+                    functions::find_spinner(name)?;
+                }
+                Term::Call(call)
+            }
+            Some(Token::Ident(name)) => Term::Var(name),
+            other => {
+                return Err(template_error(format!(
+                    "expected an expression, found {other:?}"
+                )));
+            }
+        };
+        let mut filters = vec![];
+        while let Some(token) = self.next() {
+            if token != Token::Pipe {
+                return Err(template_error(format!("unexpected {token:?}")));
+            }
+            let Some(Token::Ident(name)) = self.next() else {
+                return Err(template_error("expected a filter name after `|`"));
+            };
+            let is_style = functions::STYLE_FILTERS.iter().any(|(n, _)| *n == name);
+            if !is_style && !FILTERS.contains(&name.as_str()) {
+                return Err(template_error(format!("unknown filter `{name}`")));
+            }
+            filters.push(self.call(name)?);
+        }
+        Ok(Expr { term, filters })
+    }
+
+    /// Parses an optional `(key=value, ...)` argument list.
+    fn call(&mut self, name: String) -> Result<Call> {
+        let mut args = vec![];
+        if self.peek() == Some(&Token::LParen) {
+            self.next();
+            while self.peek() != Some(&Token::RParen) {
+                let Some(Token::Ident(key)) = self.next() else {
+                    return Err(template_error(format!(
+                        "expected a keyword argument in `{name}`"
+                    )));
+                };
+                self.expect(Token::Eq)?;
+                let Some(Token::Literal(value)) = self.next() else {
+                    return Err(template_error(format!(
+                        "expected a literal value for `{key}` in `{name}`"
+                    )));
+                };
+                args.push((key, value));
+                if self.peek() == Some(&Token::Comma) {
+                    self.next();
+                }
+            }
+            self.expect(Token::RParen)?;
+        }
+        Ok(Call { name, args })
+    }
+}
+
+impl Call {
+    fn arg(&self, key: &str) -> Option<&Literal> {
+        self.args.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn type_error(&self, key: &str, expected: &str) -> Error {
+        template_error(format!("`{}` expects `{key}` to be {expected}", self.name))
+    }
+
+    fn str(&self, key: &str) -> Result<Option<&str>> {
+        match self.arg(key) {
+            None => Ok(None),
+            Some(Literal::Str(s)) => Ok(Some(s)),
+            Some(_) => Err(self.type_error(key, "a string")),
+        }
+    }
+
+    fn int(&self, key: &str) -> Result<Option<i64>> {
+        match self.arg(key) {
+            None => Ok(None),
+            Some(Literal::Int(n)) => Ok(Some(*n)),
+            Some(_) => Err(self.type_error(key, "an integer")),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<Option<bool>> {
+        match self.arg(key) {
+            None => Ok(None),
+            Some(Literal::Bool(b)) => Ok(Some(*b)),
+            Some(_) => Err(self.type_error(key, "a boolean")),
+        }
+    }
+
+    fn call_function(&self, state: &RenderState) -> Result<String> {
+        Ok(match self.name.as_str() {
+            "spinner" => functions::spinner(state, self.str("name")?)?,
+            "progress_bar" => {
+                let args = ProgressBarArgs {
+                    hide_complete: self.bool("hide_complete")?.unwrap_or(false),
+                    flex: self.bool("flex")?.unwrap_or(false),
+                    width: self.int("width")?,
+                    style: self.str("style")?,
+                    fill: self.str("fill")?,
+                    head: self.str("head")?,
+                    empty: self.str("empty")?,
+                    left: self.str("left")?,
+                    right: self.str("right")?,
+                };
+                functions::progress_bar(state, &args)
+            }
+            "elapsed" => functions::elapsed(state),
+            "duration" => functions::duration(state),
+            "eta" => functions::eta(state, self.bool("hide_complete")?.unwrap_or(false)),
+            "rate" => functions::rate(state),
+            "bytes" => functions::bytes(
+                state,
+                self.bool("hide_complete")?.unwrap_or(false),
+                self.bool("total")?.unwrap_or(true),
+            ),
+            "percentage" => functions::percentage(
+                state,
+                self.bool("hide_complete")?.unwrap_or(false),
+                self.int("decimals")?.unwrap_or(0),
+            ),
+            "count_format" => functions::count_format(
+                state,
+                self.int("value")?,
+                self.int("decimals")?.unwrap_or(1),
+            ),
+            name => unreachable!("unknown function `{name}` passed parsing"),
+        })
+    }
+
+    fn call_filter(&self, value: String, state: &RenderState) -> Result<String> {
+        Ok(match self.name.as_str() {
+            "flex" => functions::flex(&value),
+            "flex_fill" => functions::flex_fill(&value),
+            "truncate_text" => {
+                functions::truncate_text(state, value, self.int("prefix_len")?, self.int("length")?)
+            }
+            name => {
+                let (_, style_fn) = functions::STYLE_FILTERS
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .expect("unknown filter passed parsing");
+                style_fn(&value)
+            }
+        })
+    }
+}
+
+impl Expr {
+    fn eval(&self, ctx: &Context, state: &RenderState) -> Result<String> {
+        let mut value = match &self.term {
+            Term::Var(name) => lookup(ctx, name)
+                .ok_or_else(|| template_error(format!("variable `{name}` not found")))?,
+            Term::Call(call) => call.call_function(state)?,
+            Term::Literal(literal) => literal.to_output(),
+        };
+        for filter in &self.filters {
+            value = filter.call_filter(value, state)?;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "tera")]
+fn lookup(ctx: &Context, name: &str) -> Option<String> {
+    ctx.get(name).map(|value| {
+        value
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| value.to_string())
+    })
+}
+
+#[cfg(not(feature = "tera"))]
+fn lookup(ctx: &Context, name: &str) -> Option<String> {
+    ctx.get(name).map(|value| match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    })
+}
+
+/// Template properties, used in place of `tera::Context` when clx is built
+/// without the `tera` feature.
+#[cfg(not(feature = "tera"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PropsContext {
+    data: std::collections::HashMap<String, serde_json::Value>,
+}
+
+#[cfg(not(feature = "tera"))]
+impl PropsContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<S: Into<String>, T: serde::Serialize + ?Sized>(&mut self, key: S, val: &T) {
+        let value = serde_json::to_value(val).unwrap_or(serde_json::Value::Null);
+        self.data.insert(key.into(), value);
+    }
+
+    pub fn extend(&mut self, source: PropsContext) {
+        self.data.extend(source.data);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<serde_json::Value> {
+        self.data.remove(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.data.get(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(body: &str, ctx: &Context) -> Result<String> {
+        let state = RenderState {
+            progress: Some((1, 4)),
+            width: 80,
+            ..Default::default()
+        };
+        Template::parse(body)?.render(ctx, &state)
+    }
+
+    fn ctx() -> Context {
+        let mut ctx = Context::new();
+        ctx.insert("message", "hello");
+        ctx.insert("count", &3);
+        ctx
+    }
+
+    #[test]
+    fn test_renders_variables_and_text() {
+        let out = render("[{{ message }}] {{count}} { not a tag }", &ctx()).unwrap();
+        assert_eq!(out, "[hello] 3 { not a tag }");
+    }
+
+    #[test]
+    fn test_renders_functions_and_filters() {
+        let out = render(
+            "{{ percentage(decimals=1) }} {{ 'x' | flex }} {{ message | truncate_text(length=3) }}",
+            &ctx(),
+        )
+        .unwrap();
+        assert_eq!(out, "25.0% <clx:flex>x<clx:flex> h…");
+    }
+
+    #[test]
+    fn test_trims_whitespace() {
+        let out = render("a   {{- message -}}   b", &ctx()).unwrap();
+        assert_eq!(out, "ahellob");
+    }
+
+    #[test]
+    fn test_string_literals_may_contain_braces() {
+        let out = render("{{ progress_bar(width=6, left='}}', right='{{') }}", &ctx()).unwrap();
+        assert!(out.starts_with("}}"), "{out}");
+        assert!(out.ends_with("{{"), "{out}");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for body in [
+            "{% if x %}{% endif %}",
+            "{{ message",
+            "{{ nope() }}",
+            "{{ message | nope }}",
+            "{{ spinner(name) }}",
+            "{{ spinner(name='typo') }}",
+        ] {
+            assert!(
+                matches!(Template::parse(body), Err(Error::Template(_))),
+                "{body}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_errors() {
+        assert!(matches!(
+            render("{{ missing }}", &ctx()),
+            Err(Error::Template(_))
+        ));
+        assert!(matches!(
+            render("{{ percentage(decimals='x') }}", &ctx()),
+            Err(Error::Template(_))
+        ));
+    }
+}
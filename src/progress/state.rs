@@ -6,15 +6,19 @@
 
 use std::io::Write;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, LazyLock, Mutex, OnceLock, mpsc};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use console::Term;
 
-use super::job::ProgressJob;
+use super::error_handler::report_error;
+use super::format::format_duration;
+use super::functions::RenderState;
+use super::job::{ProgressJob, ProgressStatus, run_render_error_handlers};
 use super::output::{ProgressOutput, output};
 use super::render::{refresh, refresh_once_locked, reset_terminal_resize_state};
+use super::template::{Context, TemplateEngine, Templates};
 
 // =============================================================================
 // Environment Variable Controls
@@ -141,17 +145,30 @@ pub(crate) static CRAMPED_VIEWPORT: AtomicBool = AtomicBool::new(false);
 /// Channel to notify the background thread of updates.
 static NOTIFY: Mutex<Option<mpsc::Sender<()>>> = Mutex::new(None);
 
+/// Whether a wake-up is already queued for the background thread. Updates that
+/// arrive before the next frame only need one notification between them, so a
+/// hot loop of `increment()` calls costs a flag swap rather than a send each.
+static NOTIFY_PENDING: AtomicBool = AtomicBool::new(false);
+
 /// Whether the background refresh thread is currently running.
 pub static STARTED: Mutex<bool> = Mutex::new(false);
 
 /// Whether progress rendering is temporarily paused.
 static PAUSED: AtomicBool = AtomicBool::new(false);
 
+/// Whether a prompt has the terminal to itself; see [`with_display_paused`].
+/// Changed only while both `REFRESH_LOCK` and `TERM_LOCK` are held.
+static PROMPTING: AtomicBool = AtomicBool::new(false);
+
+/// Writes above the display held back while a prompt is open.
+type HeldWrite = Box<dyn FnOnce() + Send>;
+static HELD_WRITES: Mutex<Vec<HeldWrite>> = Mutex::new(vec![]);
+
 /// Collection of all top-level progress jobs.
 pub(crate) static JOBS: Mutex<Vec<Arc<ProgressJob>>> = Mutex::new(vec![]);
 
-/// Shared Tera template engine instance.
-pub(crate) static TERA: Mutex<Option<tera::Tera>> = Mutex::new(None);
+/// Compiled job templates for every engine, shared by jobs with the same body.
+pub(crate) static TEMPLATES: Mutex<Option<Templates>> = Mutex::new(None);
 
 /// Refresh interval for the progress display.
 static INTERVAL: Mutex<Duration> = Mutex::new(Duration::from_millis(200));
@@ -159,6 +176,10 @@ static INTERVAL: Mutex<Duration> = Mutex::new(Duration::from_millis(200));
 /// OSC progress tracking state.
 pub(crate) static LAST_OSC_PERCENTAGE: Mutex<Option<u8>> = Mutex::new(None);
 
+/// Terminal title last set from the title template, or `None` while the
+/// previous title has not been saved.
+static LAST_TITLE: Mutex<Option<String>> = Mutex::new(None);
+
 /// Cache for smart refresh optimization.
 pub(crate) static LAST_OUTPUT: Mutex<String> = Mutex::new(String::new());
 
@@ -206,10 +227,21 @@ pub(crate) fn check_resize_signaled() -> bool {
 // Terminal Access
 // =============================================================================
 
-/// Returns a reference to the shared terminal instance.
-pub(crate) fn term() -> &'static Term {
-    static TERM: LazyLock<Term> = LazyLock::new(Term::stderr);
-    &TERM
+/// Terminal that progress output is written to.
+static TERM: LazyLock<RwLock<Term>> = LazyLock::new(|| RwLock::new(Term::stderr()));
+
+/// Returns a handle to the shared terminal instance.
+pub(crate) fn term() -> Term {
+    TERM.read().unwrap().clone()
+}
+
+/// Replaces the terminal that progress output is written to.
+///
+/// Output capture points this at the original stderr while fd 2 is
+/// redirected, so frames never pass through the capture pipe.
+#[cfg(unix)]
+pub(crate) fn set_term(term: Term) {
+    *TERM.write().unwrap() = term;
 }
 
 // =============================================================================
@@ -231,6 +263,63 @@ where
     result
 }
 
+/// Runs `f` with the progress display cleared and all progress output held
+/// back, then redraws the display below whatever `f` wrote.
+///
+/// Interactive prompts use this to have the terminal to themselves. No lock
+/// is held while `f` runs: frames are skipped, and lines written above the
+/// display by other threads are held back and written once `f` returns. `f`
+/// must leave the cursor at the start of a line.
+pub(crate) fn with_display_paused<R>(f: impl FnOnce(&Term) -> R) -> R {
+    let did_pause = {
+        let _refresh_guard = REFRESH_LOCK.lock().unwrap();
+        let did_pause = !is_paused();
+        if did_pause {
+            pause();
+        }
+        let _guard = TERM_LOCK.lock().unwrap();
+        PROMPTING.store(true, Ordering::Relaxed);
+        did_pause
+    };
+    #[cfg(unix)]
+    super::input::wait_for_reader();
+    let result = f(&term());
+    {
+        let _refresh_guard = REFRESH_LOCK.lock().unwrap();
+        let _guard = TERM_LOCK.lock().unwrap();
+        PROMPTING.store(false, Ordering::Relaxed);
+        let held = std::mem::take(&mut *HELD_WRITES.lock().unwrap());
+        for write in held {
+            write();
+        }
+    }
+    if did_pause {
+        resume();
+    }
+    result
+}
+
+/// Returns `true` while a prompt has the terminal to itself.
+///
+/// Read with `REFRESH_LOCK` or `TERM_LOCK` held to keep the answer until the
+/// lock is released.
+pub(crate) fn is_prompting() -> bool {
+    PROMPTING.load(Ordering::Relaxed)
+}
+
+/// Holds `write` back until the open prompt closes, or returns it if no
+/// prompt is open. The caller must hold `REFRESH_LOCK` or `TERM_LOCK`.
+pub(crate) fn hold_while_prompting<F>(write: F) -> Option<F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    if !is_prompting() {
+        return Some(write);
+    }
+    HELD_WRITES.lock().unwrap().push(Box::new(write));
+    None
+}
+
 // =============================================================================
 // Interval Configuration
 // =============================================================================
@@ -274,6 +363,33 @@ pub fn resume() {
     }
 }
 
+/// Writes a line to the progress terminal above the display, pausing the
+/// display around the write if it is running. Used by the log and tracing
+/// integrations.
+///
+/// Goes through [`term()`] rather than fd 2, which is the capture pipe while
+/// output capture is active.
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub(crate) fn write_above(line: &str) {
+    // Only resume if this call paused, so a pause made by the caller is kept.
+    let did_pause = !is_paused() && *STARTED.lock().unwrap();
+    if did_pause {
+        pause();
+    }
+    {
+        let _guard = TERM_LOCK.lock().unwrap();
+        let line = line.to_string();
+        if let Some(write) = hold_while_prompting(move || {
+            let _ = term().write_line(&line);
+        }) {
+            write();
+        }
+    }
+    if did_pause {
+        resume();
+    }
+}
+
 // =============================================================================
 // Thread Control
 // =============================================================================
@@ -284,24 +400,72 @@ pub(crate) fn notify() {
         return;
     }
     start();
-    if let Some(tx) = NOTIFY.lock().unwrap().clone() {
+    send_wake(&NOTIFY, &NOTIFY_PENDING);
+}
+
+fn notify_wait(timeout: Duration) -> bool {
+    wait_for_wake(&NOTIFY, &NOTIFY_PENDING, timeout)
+}
+
+type WakeSender = Mutex<Option<mpsc::Sender<()>>>;
+
+/// Sends a wake-up through `sender` unless one is already `pending`.
+fn send_wake(sender: &WakeSender, pending: &AtomicBool) {
+    if pending.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    if let Some(tx) = sender.lock().unwrap().clone() {
         let _ = tx.send(());
     }
 }
 
-fn notify_wait(timeout: Duration) -> bool {
+/// Waits up to `timeout` for a wake-up sent with [`send_wake`].
+fn wait_for_wake(sender: &WakeSender, pending: &AtomicBool, timeout: Duration) -> bool {
     let (tx, rx) = mpsc::channel();
-    NOTIFY.lock().unwrap().replace(tx);
-    rx.recv_timeout(timeout).is_ok()
+    sender.lock().unwrap().replace(tx);
+    // Updates made while the last frame rendered were sent to the previous
+    // channel; the pending flag is all that remains of them.
+    if pending.swap(false, Ordering::AcqRel) {
+        return true;
+    }
+    if rx.recv_timeout(timeout).is_err() {
+        return false;
+    }
+    // The wake-up has been taken, so the frame about to render covers it; a
+    // flag left set would make the next wait return at once and render again.
+    pending.store(false, Ordering::Release);
+    true
 }
 
 /// Forces an immediate refresh of the progress display.
+///
+/// In text mode this emits the latest state of jobs whose updates were held
+/// back by [`ProgressJobBuilder::min_update_interval`](super::ProgressJobBuilder::min_update_interval).
 pub fn flush() {
+    if output() == ProgressOutput::Text {
+        flush_dirty_text_jobs(&JOBS.lock().unwrap().clone());
+        return;
+    }
     if !*STARTED.lock().unwrap() {
         return;
     }
-    if let Err(err) = refresh() {
-        eprintln!("clx: {err:?}");
+    let refreshed = refresh();
+    run_render_error_handlers();
+    if let Err(err) = refreshed {
+        report_error(&err);
+    }
+}
+
+fn flush_dirty_text_jobs(jobs: &[Arc<ProgressJob>]) {
+    for job in jobs {
+        if job.dirty.swap(false, Ordering::Relaxed) {
+            let rendered = super::render::render_text_mode(job);
+            run_render_error_handlers();
+            if let Err(err) = rendered {
+                report_error(&err);
+            }
+        }
+        flush_dirty_text_jobs(&job.children());
     }
 }
 
@@ -320,6 +484,8 @@ fn start() {
 
     #[cfg(unix)]
     register_resize_handler();
+    #[cfg(unix)]
+    super::input::start_input();
 
     thread::spawn(move || {
         let mut refresh_after = Instant::now();
@@ -328,13 +494,15 @@ fn start() {
                 thread::sleep(refresh_after - Instant::now());
             }
             refresh_after = Instant::now() + interval() / 2;
-            match refresh() {
+            let refreshed = refresh();
+            run_render_error_handlers();
+            match refreshed {
                 Ok(true) => {}
                 Ok(false) => {
                     break;
                 }
                 Err(err) => {
-                    eprintln!("clx: {err:?}");
+                    report_error(&err);
                     *LINES.lock().unwrap() = 0;
                     CRAMPED_VIEWPORT.store(false, Ordering::Relaxed);
                     let _ = term().show_cursor();
@@ -351,26 +519,43 @@ fn start() {
 
 /// Stops the progress display and renders the final state.
 pub fn stop() {
+    #[cfg(unix)]
+    super::input::stop_input();
+    #[cfg(unix)]
+    super::capture::release_output();
     let refresh_guard = REFRESH_LOCK.lock().unwrap();
     STOPPING.store(true, Ordering::Relaxed);
+    let mut final_render = Ok(());
     if *STARTED.lock().unwrap()
         && !is_disabled()
         && !matches!(output(), ProgressOutput::Quiet | ProgressOutput::Text)
     {
-        let _ = refresh_once_locked();
+        final_render = refresh_once_locked();
     }
     reset_terminal_resize_state();
     drop(refresh_guard);
+    // Reported once the lock is released, since a handler that logs through
+    // the progress logger pauses the display.
+    run_render_error_handlers();
+    if let Err(err) = final_render {
+        report_error(&err);
+    }
     let _ = finish_frame();
     clear_osc_progress();
     *STARTED.lock().unwrap() = false;
     CRAMPED_VIEWPORT.store(false, Ordering::Relaxed);
+    super::summary::print_summary();
+    super::history::release();
     // Ensure all output is flushed before returning
     let _ = std::io::stderr().flush();
 }
 
 /// Stops the progress display and clears it from the screen.
 pub fn stop_clear() {
+    #[cfg(unix)]
+    super::input::stop_input();
+    #[cfg(unix)]
+    super::capture::release_output();
     let refresh_guard = REFRESH_LOCK.lock().unwrap();
     STOPPING.store(true, Ordering::Relaxed);
     reset_terminal_resize_state();
@@ -425,10 +610,12 @@ pub fn active_jobs() -> usize {
 /// let job = ProgressJobBuilder::new().start(); // new session renders normally
 /// ```
 ///
-/// This does not touch the terminal display — call [`stop_clear`] first if
-/// you also want to clear the rendered output.
+/// Finished jobs kept for the summary and the timing exports are forgotten
+/// too. This does not touch the terminal display — call [`stop_clear`] first
+/// if you also want to clear the rendered output.
 pub fn clear_jobs() {
     JOBS.lock().unwrap().clear();
+    super::history::clear();
     STOPPING.store(false, Ordering::Relaxed);
 }
 
@@ -473,55 +660,110 @@ pub(crate) fn finish_frame() -> crate::Result<()> {
 // OSC Progress
 // =============================================================================
 
-use crate::osc::{ProgressState, clear_progress, set_progress};
+use crate::osc::{
+    ProgressState, clear_progress, pop_title, push_title, send_notification, set_progress,
+    set_title_text,
+};
 
-/// Updates OSC progress based on the current progress of all jobs.
+/// Updates OSC progress and the terminal title based on the current progress
+/// of all jobs.
 pub(crate) fn update_osc_progress(jobs: &[Arc<ProgressJob>]) {
     if !crate::osc::is_enabled() || jobs.is_empty() {
         return;
     }
+    let Some((overall_percentage, has_failed_jobs)) = overall_percentage(jobs) else {
+        return;
+    };
+    update_title(jobs, overall_percentage, has_failed_jobs);
+
+    let mut last_pct = LAST_OSC_PERCENTAGE.lock().unwrap();
+    let osc_state = if has_failed_jobs {
+        ProgressState::Error
+    } else {
+        ProgressState::Normal
+    };
+
+    if *last_pct != Some(overall_percentage) || (has_failed_jobs && last_pct.is_none()) {
+        set_progress(osc_state, overall_percentage);
+        *last_pct = Some(overall_percentage);
+    }
+}
 
+/// Returns the overall percentage across all jobs and whether any failed.
+fn overall_percentage(jobs: &[Arc<ProgressJob>]) -> Option<(u8, bool)> {
     // If the first top-level job has explicit progress, use overall_progress()
     // which accounts for multi-operation tracking
-    if let Some((current, total)) = jobs[0].overall_progress() {
-        if total > 0 {
-            let overall_percentage =
-                (current as f64 / total as f64 * 100.0).clamp(0.0, 100.0) as u8;
-            let mut last_pct = LAST_OSC_PERCENTAGE.lock().unwrap();
-
-            let has_failed_jobs = check_for_failed_jobs(jobs);
-            let osc_state = if has_failed_jobs {
-                ProgressState::Error
-            } else {
-                ProgressState::Normal
-            };
-
-            if *last_pct != Some(overall_percentage) || (has_failed_jobs && last_pct.is_none()) {
-                set_progress(osc_state, overall_percentage);
-                *last_pct = Some(overall_percentage);
-            }
-            return;
-        }
+    if let Some((current, total)) = jobs[0].overall_progress()
+        && total > 0
+    {
+        let overall_percentage = (current as f64 / total as f64 * 100.0).clamp(0.0, 100.0) as u8;
+        return Some((overall_percentage, check_for_failed_jobs(jobs)));
     }
 
     // Fallback: use averaging algorithm for jobs without explicit progress
     let (total_progress, job_count, has_failed_jobs) = calculate_average_progress(jobs);
 
-    if job_count > 0 {
+    (job_count > 0).then(|| {
         let overall_percentage =
             (total_progress / job_count as f64 * 100.0).clamp(0.0, 100.0) as u8;
-        let mut last_pct = LAST_OSC_PERCENTAGE.lock().unwrap();
-
-        let osc_state = if has_failed_jobs {
-            ProgressState::Error
-        } else {
-            ProgressState::Normal
-        };
+        (overall_percentage, has_failed_jobs)
+    })
+}
 
-        if *last_pct != Some(overall_percentage) || (has_failed_jobs && last_pct.is_none()) {
-            set_progress(osc_state, overall_percentage);
-            *last_pct = Some(overall_percentage);
+/// Sets the terminal title from the template set with
+/// [`osc::set_title`](crate::osc::set_title), saving the previous title first.
+fn update_title(jobs: &[Arc<ProgressJob>], percentage: u8, has_failed_jobs: bool) {
+    let Some(template) = crate::osc::title_template() else {
+        return;
+    };
+    let title = match render_title(&template, jobs, percentage, has_failed_jobs) {
+        Ok(title) => title,
+        Err(err) => {
+            // Rendered on every refresh, so report the error only once.
+            crate::osc::title_failed();
+            report_error(&err);
+            return;
         }
+    };
+    let mut last_title = LAST_TITLE.lock().unwrap();
+    if last_title.as_deref() == Some(title.as_str()) {
+        return;
+    }
+    if last_title.is_none() {
+        push_title();
+    }
+    set_title_text(&title);
+    *last_title = Some(title);
+}
+
+fn render_title(
+    template: &str,
+    jobs: &[Arc<ProgressJob>],
+    percentage: u8,
+    has_failed_jobs: bool,
+) -> crate::Result<String> {
+    let mut ctx = Context::new();
+    ctx.insert("percentage", &percentage);
+    ctx.insert("message", &jobs[0].label());
+    ctx.insert(
+        "running",
+        &jobs.iter().filter(|job| job.is_running()).count(),
+    );
+    ctx.insert("failed", &has_failed_jobs);
+    let mut templates = TEMPLATES.lock().unwrap();
+    let templates = templates.get_or_insert_with(Templates::new);
+    templates.render(
+        TemplateEngine::default(),
+        template,
+        &mut ctx,
+        &RenderState::default(),
+    )
+}
+
+/// Restores the title saved before the first title update, if any.
+fn restore_title() {
+    if LAST_TITLE.lock().unwrap().take().is_some() {
+        pop_title();
     }
 }
 
@@ -582,12 +824,49 @@ fn calculate_average_progress(jobs: &[Arc<ProgressJob>]) -> (f64, usize, bool) {
     (total_progress, job_count, has_failed_jobs)
 }
 
-/// Clear OSC progress indicator.
+/// Sends a desktop notification for a top-level job that has just finished,
+/// if it ran longer than the threshold set with
+/// [`osc::set_notify_after`](crate::osc::set_notify_after).
+pub(crate) fn notify_job_finished(job: &ProgressJob, status: &ProgressStatus) {
+    let Some(threshold) = crate::osc::notify_after() else {
+        return;
+    };
+    if let Some((title, body)) = finish_notification(job, status, threshold) {
+        let _guard = TERM_LOCK.lock().unwrap();
+        send_notification(&title, &body);
+    }
+}
+
+/// Returns the title and body of the notification for a finished job, or
+/// `None` if it is a child job or finished sooner than `threshold`.
+fn finish_notification(
+    job: &ProgressJob,
+    status: &ProgressStatus,
+    threshold: Duration,
+) -> Option<(String, String)> {
+    // Checked by parent rather than by registration, since a `Print` job has
+    // already been removed when it finishes.
+    let duration = job.duration();
+    if duration < threshold || job.parent.upgrade().is_some() {
+        return None;
+    }
+    let duration = format_duration(duration);
+    let body = match status {
+        ProgressStatus::Done | ProgressStatus::DoneCustom(_) => format!("Done in {duration}"),
+        ProgressStatus::Warn => format!("Done with warnings in {duration}"),
+        ProgressStatus::Failed => format!("Failed after {duration}"),
+        _ => return None,
+    };
+    Some((job.label(), body))
+}
+
+/// Clear OSC progress indicator and restore the terminal title.
 pub(crate) fn clear_osc_progress() {
     if crate::osc::is_enabled() {
         clear_progress();
         *LAST_OSC_PERCENTAGE.lock().unwrap() = None;
     }
+    restore_title();
 }
 
 #[cfg(test)]
@@ -603,6 +882,47 @@ mod tests {
         assert_eq!(result, "hello");
     }
 
+    #[test]
+    fn test_writes_are_held_while_prompting() {
+        let written = Arc::new(Mutex::new(vec![]));
+        with_display_paused(|_| {
+            let w = written.clone();
+            // Would block on the display locks if the prompt held them.
+            thread::spawn(move || {
+                crate::progress::job::write_above_frame(move || w.lock().unwrap().push("line"));
+            })
+            .join()
+            .unwrap();
+            assert!(written.lock().unwrap().is_empty());
+        });
+        assert_eq!(*written.lock().unwrap(), ["line"]);
+    }
+
+    #[test]
+    fn test_notify_wakes_the_refresh_thread_once() {
+        use std::sync::atomic::AtomicUsize;
+
+        // Its own channel and flag, since other tests notify the real ones.
+        static SENDER: WakeSender = Mutex::new(None);
+        static PENDING: AtomicBool = AtomicBool::new(false);
+
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let waiter = {
+            let wakes = wakes.clone();
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    if wait_for_wake(&SENDER, &PENDING, Duration::from_millis(200)) {
+                        wakes.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        send_wake(&SENDER, &PENDING);
+        waiter.join().unwrap();
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn test_interval_get_set() {
         let original = interval();
@@ -618,4 +938,30 @@ mod tests {
         let result = check_resize_signaled();
         assert!(!result);
     }
+
+    #[test]
+    fn test_render_title() {
+        use crate::progress::ProgressJobBuilder;
+
+        let jobs = [
+            Arc::new(
+                ProgressJobBuilder::new()
+                    .prop("message", "mise install node")
+                    .build(),
+            ),
+            Arc::new(
+                ProgressJobBuilder::new()
+                    .status(ProgressStatus::Done)
+                    .build(),
+            ),
+        ];
+        let title = render_title(
+            "[{{ percentage }}%] {{ message }} ({{ running }} running)",
+            &jobs,
+            42,
+            false,
+        )
+        .unwrap();
+        assert_eq!(title, "[42%] mise install node (1 running)");
+    }
 }
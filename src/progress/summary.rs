@@ -0,0 +1,351 @@
+//! Summary of all jobs printed when the display stops.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::{Result, style};
+
+use super::error_handler::report_error;
+use super::format::format_duration;
+use super::functions::{RenderState, status_icon};
+use super::history::{self, JobRecord};
+use super::job::ProgressStatus;
+use super::output::{ProgressOutput, output};
+use super::state::{TEMPLATES, TERM_LOCK, hold_while_prompting, is_disabled, is_prompting, term};
+use super::template::{Context, TemplateEngine, Templates};
+
+/// Default summary template.
+const DEFAULT_SUMMARY_BODY: &str = "{{ counts }} in {{ elapsed }}\n{{ slowest }}\n{{ problems }}";
+
+/// Number of jobs listed in `slowest` by default.
+const DEFAULT_SLOWEST: usize = 3;
+
+static SUMMARY: Mutex<Option<Summary>> = Mutex::new(None);
+
+/// A report of how every job ended, printed by [`stop`](super::stop).
+///
+/// The report is rendered from a template, like a job body, with these
+/// variables:
+///
+/// | Variable | Value |
+/// |----------|-------|
+/// | `total`, `done`, `warned`, `failed`, `cancelled`, `unfinished` | Job counts |
+/// | `elapsed` | Time from the first job's start until `stop` |
+/// | `counts` | The non-zero counts, e.g. `4 jobs: 3 done, 1 failed` |
+/// | `slowest` | The longest-running jobs with their durations |
+/// | `problems` | One line per failed or warned job, with its message |
+/// | `jobs` | Every job as `{ message, status, duration }`, for Tera loops |
+///
+/// Every job is counted, except hidden ones, including jobs that have left
+/// the tree, such as finished [`Print`](super::ProgressJobDoneBehavior::Print)
+/// jobs, as long as they finished after the summary was set. Blank lines in
+/// the output are dropped, so sections with nothing to report disappear.
+///
+/// ```rust,no_run
+/// use clx::progress::{Summary, set_summary, stop};
+///
+/// set_summary(Some(Summary::new().slowest(5)));
+/// // ... run jobs ...
+/// stop(); // prints e.g. "4 jobs: 3 done, 1 failed in 12s"
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Summary {
+    body: String,
+    engine: TemplateEngine,
+    slowest: usize,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Summary {
+    /// Creates a summary with the default template.
+    pub fn new() -> Self {
+        Self {
+            body: DEFAULT_SUMMARY_BODY.to_string(),
+            engine: TemplateEngine::default(),
+            slowest: DEFAULT_SLOWEST,
+        }
+    }
+
+    /// Sets the template the summary is rendered from.
+    pub fn body<S: Into<String>>(mut self, body: S) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets the template engine used to render the body.
+    pub fn engine(mut self, engine: TemplateEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Sets how many jobs `slowest` lists. Zero leaves it empty.
+    pub fn slowest(mut self, count: usize) -> Self {
+        self.slowest = count;
+        self
+    }
+
+    fn render(&self, jobs: &[JobRecord], now: Instant) -> Result<Option<String>> {
+        let jobs = jobs
+            .iter()
+            .filter(|job| job.status != ProgressStatus::Hide)
+            .collect::<Vec<_>>();
+        let Some(first_start) = jobs.iter().map(|job| job.start).min() else {
+            return Ok(None);
+        };
+        let entries = jobs
+            .iter()
+            .map(|job| Entry::new(job, now))
+            .collect::<Vec<_>>();
+
+        let count =
+            |f: fn(&ProgressStatus) -> bool| entries.iter().filter(|e| f(&e.status)).count();
+        let done = count(|s| matches!(s, ProgressStatus::Done | ProgressStatus::DoneCustom(_)));
+        let warned = count(|s| *s == ProgressStatus::Warn);
+        let failed = count(|s| *s == ProgressStatus::Failed);
+        let cancelled = count(|s| *s == ProgressStatus::Cancelled);
+        let unfinished = entries.len() - done - warned - failed - cancelled;
+
+        let mut ctx = Context::new();
+        ctx.insert("total", &entries.len());
+        ctx.insert("done", &done);
+        ctx.insert("warned", &warned);
+        ctx.insert("failed", &failed);
+        ctx.insert("cancelled", &cancelled);
+        ctx.insert("unfinished", &unfinished);
+        ctx.insert(
+            "elapsed",
+            &format_duration(now.saturating_duration_since(first_start)),
+        );
+        ctx.insert(
+            "counts",
+            &format_counts(
+                entries.len(),
+                &[
+                    (done, style::egreen("done").to_string()),
+                    (warned, style::eyellow("warned").to_string()),
+                    (failed, style::ered("failed").to_string()),
+                    (cancelled, style::edim("cancelled").to_string()),
+                    (unfinished, "unfinished".to_string()),
+                ],
+            ),
+        );
+        ctx.insert("slowest", &self.format_slowest(&entries));
+        ctx.insert("problems", &format_problems(&entries));
+        ctx.insert(
+            "jobs",
+            &entries
+                .iter()
+                .map(|e| {
+                    json!({
+                        "message": e.message,
+                        "status": format!("{:?}", e.status),
+                        "duration": format_duration(e.duration),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let mut templates = TEMPLATES.lock().unwrap();
+        let templates = templates.get_or_insert_with(Templates::new);
+        let output =
+            templates.render(self.engine, &self.body, &mut ctx, &RenderState::default())?;
+        Ok(Some(
+            output
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+
+    fn format_slowest(&self, entries: &[Entry]) -> String {
+        if self.slowest == 0 || entries.len() < 2 {
+            return String::new();
+        }
+        let mut slowest = entries.iter().collect::<Vec<_>>();
+        slowest.sort_by_key(|e| std::cmp::Reverse(e.duration));
+        let list = slowest
+            .iter()
+            .take(self.slowest)
+            .map(|e| format!("{} {}", e.message, style::edim(format_duration(e.duration))))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{} {list}", style::edim("slowest:"))
+    }
+}
+
+/// What the summary reports about one job.
+struct Entry {
+    message: String,
+    status: ProgressStatus,
+    duration: Duration,
+}
+
+impl Entry {
+    fn new(job: &JobRecord, now: Instant) -> Self {
+        let end = job.end.unwrap_or(now);
+        Self {
+            message: job.label.clone(),
+            status: job.status.clone(),
+            duration: end.saturating_duration_since(job.start),
+        }
+    }
+}
+
+fn format_counts(total: usize, counts: &[(usize, String)]) -> String {
+    let noun = if total == 1 { "job" } else { "jobs" };
+    let counts = counts
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{total} {noun}: {counts}")
+}
+
+fn format_problems(entries: &[Entry]) -> String {
+    entries
+        .iter()
+        .filter(|e| matches!(e.status, ProgressStatus::Failed | ProgressStatus::Warn))
+        .map(|e| {
+            format!(
+                "{} {} {}",
+                status_icon(&e.status).unwrap_or_default(),
+                e.message,
+                style::edim(format_duration(e.duration))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prints a summary of the registered jobs when [`stop`](super::stop) is
+/// called, or stops printing one with `None`.
+///
+/// The summary is printed below the final frame in
+/// [`UI`](ProgressOutput::UI) mode and after the last line in
+/// [`Text`](ProgressOutput::Text) mode, but not when output is quiet or
+/// disabled.
+pub fn set_summary(summary: Option<Summary>) {
+    *SUMMARY.lock().unwrap() = summary;
+}
+
+/// Returns `true` if a summary is set, so finished jobs are recorded for it.
+pub(crate) fn is_set() -> bool {
+    SUMMARY.lock().unwrap().is_some()
+}
+
+/// Prints the configured summary, if any. Called by `stop`.
+pub(crate) fn print_summary() {
+    let Some(summary) = SUMMARY.lock().unwrap().clone() else {
+        return;
+    };
+    if is_disabled() || output() == ProgressOutput::Quiet {
+        return;
+    }
+    match summary.render(&history::records(), Instant::now()) {
+        Ok(Some(text)) if !text.is_empty() => {
+            let guard = TERM_LOCK.lock().unwrap();
+            let written = if is_prompting() {
+                // Written once the prompt closes, where errors are dropped.
+                let _ = hold_while_prompting(move || {
+                    let _ = term().write_line(&text);
+                });
+                Ok(())
+            } else {
+                term().write_line(&text)
+            };
+            drop(guard);
+            if let Err(err) = written {
+                report_error(&err.into());
+            }
+        }
+        Ok(_) => {}
+        Err(err) => report_error(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::progress::ProgressJobBuilder;
+    use crate::progress::job::ProgressJob;
+
+    fn job(message: &str, status: ProgressStatus) -> Arc<ProgressJob> {
+        Arc::new(
+            ProgressJobBuilder::new()
+                .prop("message", message)
+                .status(status)
+                .build(),
+        )
+    }
+
+    fn render(summary: &Summary, jobs: &[Arc<ProgressJob>]) -> String {
+        let now = Instant::now() + Duration::from_secs(5);
+        let output = summary
+            .render(&history::merge(vec![], jobs), now)
+            .unwrap()
+            .unwrap();
+        console::strip_ansi_codes(&output).into_owned()
+    }
+
+    #[test]
+    fn test_default_summary() {
+        let build = job("build", ProgressStatus::Running);
+        build.add(
+            ProgressJobBuilder::new()
+                .prop("message", "lint")
+                .status(ProgressStatus::Warn)
+                .build(),
+        );
+        let jobs = [build, job("test", ProgressStatus::Failed)];
+
+        let output = render(&Summary::new().slowest(1), &jobs);
+        assert_eq!(
+            output,
+            "3 jobs: 1 warned, 1 failed, 1 unfinished in 5s\n\
+             slowest: build 5s\n\
+             ⚠ lint 0s\n\
+             ✗ test 0s"
+        );
+    }
+
+    #[test]
+    fn test_summary_drops_empty_sections() {
+        let jobs = [job("build", ProgressStatus::Done)];
+        assert_eq!(render(&Summary::new(), &jobs), "1 job: 1 done in 5s");
+    }
+
+    #[test]
+    fn test_custom_summary_body() {
+        let jobs = [
+            job("a", ProgressStatus::Done),
+            job("b", ProgressStatus::Cancelled),
+            job("c", ProgressStatus::Hide),
+        ];
+        let summary = Summary::new()
+            .engine(TemplateEngine::Simple)
+            .body("{{ done }}/{{ total }} done, {{ cancelled }} cancelled");
+        assert_eq!(render(&summary, &jobs), "1/2 done, 1 cancelled");
+    }
+
+    #[test]
+    fn test_summary_without_jobs() {
+        assert!(
+            Summary::new()
+                .render(&[], Instant::now())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
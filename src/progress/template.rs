@@ -0,0 +1,234 @@
+//! Template engine selection and the compiled template cache.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "tera")]
+use tera::Tera;
+
+use crate::Result;
+
+use super::functions::RenderState;
+use super::simple;
+
+#[cfg(not(feature = "tera"))]
+pub(crate) use super::simple::PropsContext as Context;
+#[cfg(feature = "tera")]
+pub(crate) use tera::Context;
+
+/// Template engine used to render a job's body.
+///
+/// Both engines provide the same functions (`spinner()`, `progress_bar()`, ...)
+/// and filters (`flex`, `cyan`, ...). Tera additionally supports its full
+/// language, such as `{% if %}` blocks and its own filters. The simple engine
+/// only understands `{{ ... }}` expressions but avoids the Tera dependency.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateEngine {
+    /// Full [Tera](https://keats.github.io/tera/) templates (requires the `tera` feature).
+    #[cfg(feature = "tera")]
+    #[default]
+    Tera,
+    /// The built-in engine: variables, clx functions, and clx filters only.
+    #[cfg_attr(not(feature = "tera"), default)]
+    Simple,
+}
+
+/// Upper bound on cached templates per engine. Bodies that embed changing
+/// values would otherwise grow the cache without limit; past this the cache
+/// starts over.
+const MAX_CACHED_TEMPLATES: usize = 256;
+
+/// Compiled templates for every engine, keyed by body.
+///
+/// Any number of jobs sharing a body share one compiled template.
+pub(crate) struct Templates {
+    #[cfg(feature = "tera")]
+    tera: Tera,
+    /// Name of the Tera template compiled from each body.
+    #[cfg(feature = "tera")]
+    tera_names: HashMap<String, String>,
+    simple: HashMap<String, simple::Template>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "tera")]
+            tera: super::tera_setup::new_tera(),
+            #[cfg(feature = "tera")]
+            tera_names: HashMap::new(),
+            simple: HashMap::new(),
+        }
+    }
+
+    /// Compiles `body` with `engine` if it is not cached yet.
+    ///
+    /// This catches syntax errors, unknown functions or filters, and unknown
+    /// spinners passed as a literal `name`.
+    pub fn compile(&mut self, engine: TemplateEngine, body: &str) -> Result<()> {
+        match engine {
+            #[cfg(feature = "tera")]
+            TemplateEngine::Tera => self.compile_tera(body).map(|_| ()),
+            TemplateEngine::Simple => self.compile_simple(body).map(|_| ()),
+        }
+    }
+
+    /// Renders `body` with `engine`, compiling it first if needed.
+    pub fn render(
+        &mut self,
+        engine: TemplateEngine,
+        body: &str,
+        ctx: &mut Context,
+        state: &RenderState,
+    ) -> Result<String> {
+        match engine {
+            #[cfg(feature = "tera")]
+            TemplateEngine::Tera => {
+                super::tera_setup::insert_render_state(ctx, state);
+                let name = self.compile_tera(body)?;
+                Ok(self.tera.render(&name, ctx)?)
+            }
+            TemplateEngine::Simple => self.compile_simple(body)?.render(ctx, state),
+        }
+    }
+
+    fn compile_simple(&mut self, body: &str) -> Result<&simple::Template> {
+        if !self.simple.contains_key(body) {
+            if self.simple.len() >= MAX_CACHED_TEMPLATES {
+                self.simple.clear();
+            }
+            let template = simple::Template::parse(body)?;
+            self.simple.insert(body.to_string(), template);
+        }
+        Ok(&self.simple[body])
+    }
+
+    /// Returns the name of the compiled Tera template for `body`, compiling it
+    /// first if needed.
+    #[cfg(feature = "tera")]
+    fn compile_tera(&mut self, body: &str) -> Result<String> {
+        if let Some(name) = self.tera_names.get(body) {
+            return Ok(name.clone());
+        }
+        for name in literal_spinner_names(body) {
+            super::functions::find_spinner(name)?;
+        }
+        if self.tera_names.len() >= MAX_CACHED_TEMPLATES {
+            self.tera = super::tera_setup::new_tera();
+            self.tera_names.clear();
+        }
+        let name = format!("body_{}", self.tera_names.len());
+        self.tera.add_raw_template(&name, body)?;
+        self.tera_names.insert(body.to_string(), name.clone());
+        Ok(name)
+    }
+}
+
+/// Returns the string literals passed as `name` to spinner() calls in a Tera
+/// body.
+///
+/// Tera does not expose the parsed template, so this scans the source. It
+/// stops at the first argument of a call that is not a string literal.
+#[cfg(feature = "tera")]
+fn literal_spinner_names(body: &str) -> Vec<&str> {
+    let mut names = vec![];
+    for (start, call) in body.match_indices("spinner(") {
+        if body[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let mut args = &body[start + call.len()..];
+        loop {
+            args = args.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+            let key_len = args
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(args.len());
+            let (key, rest) = args.split_at(key_len);
+            let Some(value) = rest.trim_start().strip_prefix('=') else {
+                break;
+            };
+            let value = value.trim_start();
+            let Some(quote) = value
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '\'' | '"' | '`'))
+            else {
+                break;
+            };
+            let Some(len) = value[1..].find(quote) else {
+                break;
+            };
+            if key == "name" {
+                names.push(&value[1..=len]);
+            }
+            args = &value[len + 2..];
+        }
+    }
+    names
+}
+
+#[cfg(all(test, feature = "tera"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_tera_shares_templates_between_identical_bodies() {
+        let mut templates = Templates::new();
+        let a = templates.compile_tera("{{ message }}").unwrap();
+        let b = templates.compile_tera("{{ message }}").unwrap();
+        let c = templates.compile_tera("{{ message }}!").unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(templates.tera_names["{{ message }}"], a);
+        assert_eq!(templates.tera_names["{{ message }}!"], c);
+        assert_eq!(templates.tera.get_template_names().count(), 2);
+    }
+
+    #[test]
+    fn compile_tera_bounds_the_template_cache() {
+        let mut templates = Templates::new();
+        for i in 0..=MAX_CACHED_TEMPLATES {
+            templates
+                .compile_tera(&format!("{{{{ message }}}} {i}"))
+                .unwrap();
+        }
+
+        assert_eq!(templates.tera.get_template_names().count(), 1);
+        assert_eq!(templates.tera_names.len(), 1);
+    }
+
+    #[test]
+    fn compile_tera_rejects_unknown_spinner_names() {
+        let mut templates = Templates::new();
+        for body in [
+            "{{ spinner(name='typo') }}",
+            "{{ spinner( name = \"typo\" ) }}",
+            "{% if x %}{{ spinner(name='dot') }}{% endif %}{{ spinner(name=`typo`) }}",
+        ] {
+            assert!(
+                matches!(templates.compile_tera(body), Err(crate::Error::Template(_))),
+                "{body}"
+            );
+        }
+        assert!(templates.compile_tera("{{ spinner(name='dot') }}").is_ok());
+        assert!(templates.compile_tera("{{ spinner(name=n) }}").is_ok());
+    }
+
+    #[test]
+    fn literal_spinner_names_reads_only_spinner_calls() {
+        assert_eq!(
+            literal_spinner_names("{{ my_spinner(name='a') }} {{ spinner(x=1) }}"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            literal_spinner_names("{{ spinner(style='x', name=\"a\") }} {{ spinner(name='b') }}"),
+            ["a", "b"]
+        );
+    }
+}
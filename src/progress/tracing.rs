@@ -0,0 +1,333 @@
+//! Integration with the `tracing` crate.
+//!
+//! When enabled (via the `tracing` feature), this module provides a
+//! [`tracing_subscriber::Layer`] that writes events above the progress display
+//! the same way [`ProgressLogger`](super::ProgressLogger) does for `log`, and
+//! can optionally show spans as progress jobs.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use clx::progress::ProgressLayer;
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(ProgressLayer::new().with_span_jobs(true))
+//!     .init();
+//!
+//! let span = tracing::info_span!("download", file = "a.tar.gz");
+//! let _guard = span.enter(); // shows "download" as a job
+//! tracing::info!("connecting"); // printed above the progress display
+//! ```
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::job::{
+    ProgressJob, ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus, start_job,
+};
+use super::spinners::DEFAULT_BODY;
+use super::state::write_above;
+use crate::style;
+
+/// A `tracing` layer that integrates with the progress display system.
+///
+/// Events are written to stderr with progress paused, so they are never
+/// overwritten by a redraw. Filter events with the usual `tracing_subscriber`
+/// filters, such as [`Layer::with_filter`].
+///
+/// With [`with_span_jobs`](Self::with_span_jobs), each span becomes a progress
+/// job when it is first entered: a child of the job of its nearest ancestor
+/// span, or a top-level job if it has none. The job's `message` is the span
+/// name, or its `message` field if it has one, and every other span field is
+/// set as a property. When the span closes, the job is marked
+/// [`Done`](ProgressStatus::Done) and, by default, removed from the display;
+/// see [`with_span_done`](Self::with_span_done).
+#[derive(Debug, Clone)]
+pub struct ProgressLayer {
+    span_jobs: bool,
+    span_body: String,
+    span_done: ProgressJobDoneBehavior,
+}
+
+impl Default for ProgressLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressLayer {
+    /// Creates a layer that writes events and does not create jobs for spans.
+    pub fn new() -> Self {
+        Self {
+            span_jobs: false,
+            span_body: DEFAULT_BODY.clone(),
+            span_done: ProgressJobDoneBehavior::Hide,
+        }
+    }
+
+    /// Sets whether spans are shown as progress jobs.
+    pub fn with_span_jobs(mut self, enabled: bool) -> Self {
+        self.span_jobs = enabled;
+        self
+    }
+
+    /// Sets the template body used for span jobs.
+    ///
+    /// Span fields are available as properties, e.g. `{{ file }}`.
+    pub fn with_span_body(mut self, body: impl Into<String>) -> Self {
+        self.span_body = body.into();
+        self
+    }
+
+    /// Sets what happens to a span job when its span closes.
+    ///
+    /// Defaults to [`Hide`](ProgressJobDoneBehavior::Hide), which also drops
+    /// the job, so a long-running program does not keep a job for every span
+    /// it has entered. [`Keep`](ProgressJobDoneBehavior::Keep) leaves finished
+    /// spans in the frame, and [`Print`](ProgressJobDoneBehavior::Print)
+    /// moves them to scrollback.
+    pub fn with_span_done(mut self, behavior: ProgressJobDoneBehavior) -> Self {
+        self.span_done = behavior;
+        self
+    }
+
+    fn format_event(event: &Event<'_>) -> String {
+        let level_str = match *event.metadata().level() {
+            Level::ERROR => style::ered("ERROR").to_string(),
+            Level::WARN => style::eyellow("WARN").to_string(),
+            Level::INFO => style::ecyan("INFO").to_string(),
+            Level::DEBUG => style::edim("DEBUG").to_string(),
+            Level::TRACE => style::edim("TRACE").to_string(),
+        };
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let mut message = format!("{} {}", level_str, fields.message.unwrap_or_default());
+        for (name, value) in &fields.values {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            message.push(' ');
+            message.push_str(&style::edim(format!("{name}={value}")).to_string());
+        }
+        message
+    }
+}
+
+/// Fields recorded for a span, and its job once it has been entered.
+struct SpanJob {
+    name: &'static str,
+    fields: Fields,
+    entered: bool,
+    job: Option<Arc<ProgressJob>>,
+}
+
+impl SpanJob {
+    fn message(&self) -> &str {
+        self.fields.message.as_deref().unwrap_or(self.name)
+    }
+}
+
+/// Collects field values for event messages and job properties.
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    values: Vec<(&'static str, Value)>,
+}
+
+impl Fields {
+    fn set(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = Some(match value {
+                Value::String(s) => s,
+                value => value.to_string(),
+            });
+        } else if let Some(entry) = self.values.iter_mut().find(|(n, _)| *n == field.name()) {
+            entry.1 = value;
+        } else {
+            self.values.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field, Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, value.into());
+    }
+}
+
+impl<S> Layer<S> for ProgressLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !self.span_jobs {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanJob {
+            name: attrs.metadata().name(),
+            fields,
+            entered: false,
+            job: None,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        let job = {
+            let mut extensions = span.extensions_mut();
+            let Some(span_job) = extensions.get_mut::<SpanJob>() else {
+                return;
+            };
+            if let Some(message) = &fields.message {
+                span_job.fields.message = Some(message.clone());
+            }
+            for (name, value) in &fields.values {
+                if let Some(entry) = span_job.fields.values.iter_mut().find(|(n, _)| n == name) {
+                    entry.1 = value.clone();
+                } else {
+                    span_job.fields.values.push((name, value.clone()));
+                }
+            }
+            span_job.job.clone()
+        };
+        // Setting a prop can render, so the span's extensions are released
+        // first.
+        if let Some(job) = job {
+            if let Some(message) = &fields.message {
+                job.prop("message", message);
+            }
+            for (name, value) in &fields.values {
+                job.prop(*name, value);
+            }
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.scope().skip(1).find_map(|ancestor| {
+            ancestor
+                .extensions()
+                .get::<SpanJob>()
+                .and_then(|span_job| span_job.job.clone())
+        });
+        let builder = {
+            let mut extensions = span.extensions_mut();
+            let Some(span_job) = extensions.get_mut::<SpanJob>() else {
+                return;
+            };
+            if span_job.entered {
+                return;
+            }
+            span_job.entered = true;
+            let mut builder = ProgressJobBuilder::new()
+                .body(self.span_body.clone())
+                .on_done(self.span_done.clone())
+                .prop("message", span_job.message());
+            for (name, value) in &span_job.fields.values {
+                builder = builder.prop(*name, value);
+            }
+            builder
+        };
+        // Starting the job renders it, so the span's extensions are released
+        // first.
+        let job = match parent {
+            Some(parent) => parent.add(builder.build()),
+            None => start_job(builder.build()),
+        };
+        if let Some(span_job) = span.extensions_mut().get_mut::<SpanJob>() {
+            span_job.job = Some(job);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let job = span
+            .extensions_mut()
+            .get_mut::<SpanJob>()
+            .and_then(|span_job| span_job.job.take());
+        if let Some(job) = job {
+            job.set_status(ProgressStatus::Done);
+            if self.span_done == ProgressJobDoneBehavior::Hide {
+                job.remove();
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        write_above(&Self::format_event(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Captures the formatted events passed through a layer.
+    struct Capture(std::sync::Mutex<Vec<String>>);
+
+    impl<S: Subscriber> Layer<S> for &'static Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let message = ProgressLayer::format_event(event);
+            self.0
+                .lock()
+                .unwrap()
+                .push(console::strip_ansi_codes(&message).into());
+        }
+    }
+
+    #[test]
+    fn test_format_event_includes_fields() {
+        let capture: &'static Capture = Box::leak(Box::new(Capture(Default::default())));
+        let subscriber = tracing_subscriber::registry().with(capture);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(file = "a.txt", size = 3, ok = true, "copied {}", 1);
+        });
+        assert_eq!(
+            *capture.0.lock().unwrap(),
+            ["WARN copied 1 file=a.txt size=3 ok=true"]
+        );
+    }
+}
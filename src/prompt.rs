@@ -0,0 +1,548 @@
+//! Interactive prompts that cooperate with the live progress display.
+//!
+//! Each prompt clears the progress display, asks its question on the
+//! terminal, prints a one-line summary of the answer, and then lets the
+//! display redraw below it. Progress output from other threads is held back
+//! while the prompt is open.
+//!
+//! ```rust,no_run
+//! use clx::prompt::{Confirm, Select};
+//!
+//! let target = Select::new("Deploy to")
+//!     .items(["staging", "production"])
+//!     .interact()?;
+//! if Confirm::new("Continue?").default(false).interact()? {
+//!     // ...
+//! }
+//! # Ok::<(), clx::Error>(())
+//! ```
+//!
+//! # Non-interactive use
+//!
+//! Prompts are only shown when stdin and the progress terminal are both
+//! terminals and the progress output is [`ProgressOutput::UI`]. Otherwise,
+//! such as in CI or with [`ProgressOutput::Text`] or [`ProgressOutput::Quiet`],
+//! each prompt answers with its default, or fails with
+//! [`Error::NotInteractive`] if it has none.
+//!
+//! Pressing Escape or Ctrl-C fails the prompt with [`Error::PromptCancelled`].
+
+use std::io::{self, IsTerminal};
+
+use console::{Key, Term};
+
+use crate::progress::{ProgressOutput, output, term, with_display_paused};
+use crate::style;
+use crate::{Error, Result};
+
+/// Turns off echo and line buffering on stdin while a prompt is open.
+///
+/// Keys are read one at a time in raw mode, but the terminal returns to its
+/// normal mode between reads. Without this, keys typed ahead would be echoed
+/// over the prompt.
+struct NoEcho {
+    #[cfg(unix)]
+    original: Option<nix::sys::termios::Termios>,
+}
+
+impl NoEcho {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use nix::sys::termios::{LocalFlags, SetArg, tcgetattr, tcsetattr};
+
+            let stdin = io::stdin();
+            let original = tcgetattr(&stdin).ok();
+            if let Some(original) = &original {
+                let mut termios = original.clone();
+                termios
+                    .local_flags
+                    .remove(LocalFlags::ECHO | LocalFlags::ICANON);
+                let _ = tcsetattr(&stdin, SetArg::TCSADRAIN, &termios);
+            }
+            Self { original }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+}
+
+impl Drop for NoEcho {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            let _ = nix::sys::termios::tcsetattr(
+                io::stdin(),
+                nix::sys::termios::SetArg::TCSADRAIN,
+                original,
+            );
+        }
+    }
+}
+
+/// Runs a prompt with the progress display paused and echo turned off.
+fn with_prompt<T>(f: impl FnOnce(&Term) -> Result<T>) -> Result<T> {
+    with_display_paused(|term| {
+        let _no_echo = NoEcho::new();
+        f(term)
+    })
+}
+
+/// Returns `true` if prompts can be shown and answered.
+fn is_interactive() -> bool {
+    output() == ProgressOutput::UI && io::stdin().is_terminal() && term().is_term()
+}
+
+fn header(term: &Term, prompt: &str, hint: &str) -> io::Result<()> {
+    term.write_str(&format!(
+        "{} {} {}",
+        style::ecyan("?"),
+        style::ebold(prompt),
+        style::edim(hint)
+    ))
+}
+
+/// Replaces the prompt with a summary of the answer.
+fn report(term: &Term, prompt: &str, answer: &str) -> io::Result<()> {
+    term.clear_line()?;
+    term.write_line(&format!(
+        "{} {} {}",
+        style::egreen("✔"),
+        style::ebold(prompt),
+        style::ecyan(answer)
+    ))
+}
+
+fn cancelled<T>(term: &Term, prompt: &str) -> Result<T> {
+    term.clear_line()?;
+    Err(Error::PromptCancelled(prompt.to_string()))
+}
+
+/// A yes/no question.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Confirm {
+    prompt: String,
+    default: Option<bool>,
+}
+
+impl Confirm {
+    /// Creates a yes/no prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            default: None,
+        }
+    }
+
+    /// Sets the answer used for Enter and when there is no terminal to ask on.
+    pub fn default(mut self, default: bool) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// Asks the question and returns the answer.
+    ///
+    /// # Errors
+    ///
+    /// See the [module documentation](self#non-interactive-use).
+    pub fn interact(&self) -> Result<bool> {
+        if !is_interactive() {
+            return self
+                .default
+                .ok_or_else(|| Error::NotInteractive(self.prompt.clone()));
+        }
+        with_prompt(|term| {
+            let hint = match self.default {
+                Some(true) => "(Y/n) ",
+                Some(false) => "(y/N) ",
+                None => "(y/n) ",
+            };
+            header(term, &self.prompt, hint)?;
+            let answer = loop {
+                match term.read_key_raw()? {
+                    Key::Char('y' | 'Y') => break true,
+                    Key::Char('n' | 'N') => break false,
+                    Key::Enter => {
+                        if let Some(default) = self.default {
+                            break default;
+                        }
+                    }
+                    Key::Escape | Key::CtrlC => return cancelled(term, &self.prompt),
+                    _ => {}
+                }
+            };
+            report(term, &self.prompt, if answer { "yes" } else { "no" })?;
+            Ok(answer)
+        })
+    }
+}
+
+/// A line of text input.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Text {
+    prompt: String,
+    default: Option<String>,
+}
+
+impl Text {
+    /// Creates a text prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            default: None,
+        }
+    }
+
+    /// Sets the answer used when the input is left empty and when there is no
+    /// terminal to ask on.
+    pub fn default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Asks for the text and returns it.
+    ///
+    /// Without a default, an empty input is returned as an empty string.
+    ///
+    /// # Errors
+    ///
+    /// See the [module documentation](self#non-interactive-use).
+    pub fn interact(&self) -> Result<String> {
+        if !is_interactive() {
+            return self
+                .default
+                .clone()
+                .ok_or_else(|| Error::NotInteractive(self.prompt.clone()));
+        }
+        with_prompt(|term| {
+            let hint = match &self.default {
+                Some(default) => format!("({default}) "),
+                None => String::new(),
+            };
+            header(term, &self.prompt, &hint)?;
+            let mut input = String::new();
+            loop {
+                match term.read_key_raw()? {
+                    Key::Char(c) if !c.is_control() => {
+                        input.push(c);
+                        term.write_str(c.encode_utf8(&mut [0; 4]))?;
+                    }
+                    Key::Backspace => {
+                        if let Some(c) = input.pop() {
+                            term.clear_chars(console::measure_text_width(&c.to_string()))?;
+                        }
+                    }
+                    Key::Enter => break,
+                    Key::Escape | Key::CtrlC => return cancelled(term, &self.prompt),
+                    _ => {}
+                }
+            }
+            if input.is_empty()
+                && let Some(default) = &self.default
+            {
+                input = default.clone();
+            }
+            report(term, &self.prompt, &input)?;
+            Ok(input)
+        })
+    }
+}
+
+/// Draws a list of items below a header, redrawing it in place on each key.
+struct ListView<'a> {
+    term: &'a Term,
+    /// Lines drawn by the last call to `draw`.
+    lines: usize,
+}
+
+impl<'a> ListView<'a> {
+    fn new(term: &'a Term) -> io::Result<Self> {
+        term.hide_cursor()?;
+        Ok(Self { term, lines: 0 })
+    }
+
+    /// Draws the header and as many rows as fit, scrolled to keep `cursor`
+    /// visible.
+    fn draw(&mut self, prompt: &str, hint: &str, rows: &[String], cursor: usize) -> io::Result<()> {
+        self.clear()?;
+        let (height, width) = self.term.size();
+        let visible = (height as usize).saturating_sub(2).max(1);
+        let start = (cursor + 1).saturating_sub(visible);
+        header(self.term, prompt, hint)?;
+        self.term.write_line("")?;
+        for row in rows.iter().skip(start).take(visible) {
+            self.term
+                .write_line(&console::truncate_str(row, width as usize, "…"))?;
+        }
+        self.lines = 1 + rows.len().min(visible);
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        if self.lines > 0 {
+            self.term.move_cursor_up(self.lines)?;
+            self.term.clear_to_end_of_screen()?;
+            self.lines = 0;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ListView<'_> {
+    fn drop(&mut self) {
+        let _ = self.clear();
+        let _ = self.term.show_cursor();
+    }
+}
+
+/// Moves `cursor` for navigation keys, wrapping around the ends of the list.
+/// Returns `false` for any other key.
+fn navigate(key: &Key, cursor: &mut usize, len: usize) -> bool {
+    match key {
+        Key::ArrowUp | Key::Char('k') | Key::BackTab => *cursor = (*cursor + len - 1) % len,
+        Key::ArrowDown | Key::Char('j') | Key::Tab => *cursor = (*cursor + 1) % len,
+        Key::Home => *cursor = 0,
+        Key::End => *cursor = len - 1,
+        _ => return false,
+    }
+    true
+}
+
+/// Checks that a list prompt has items to choose from and that `default`,
+/// if any, is one of them. Done before the terminal is touched.
+fn check_items(prompt: &str, items: &[String], default: Option<usize>) -> Result<()> {
+    if items.is_empty() {
+        return Err(Error::NoItems(prompt.to_string()));
+    }
+    if default.is_some_and(|i| i >= items.len()) {
+        return Err(Error::InvalidDefault(prompt.to_string()));
+    }
+    Ok(())
+}
+
+/// A choice of one item from a list.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Select {
+    prompt: String,
+    items: Vec<String>,
+    default: Option<usize>,
+}
+
+impl Select {
+    /// Creates a single-choice prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            items: vec![],
+            default: None,
+        }
+    }
+
+    /// Adds items to choose from.
+    pub fn items<I, S>(mut self, items: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.items.extend(items.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the index of the initially highlighted item, which is also the
+    /// answer when there is no terminal to ask on.
+    pub fn default(mut self, index: usize) -> Self {
+        self.default = Some(index);
+        self
+    }
+
+    /// Shows the list and returns the index of the chosen item.
+    ///
+    /// Arrow keys (or `j`/`k`) move the highlight and Enter chooses.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::NoItems`] if there are no items and with
+    /// [`Error::InvalidDefault`] if the default is out of range; otherwise see
+    /// the [module documentation](self#non-interactive-use).
+    pub fn interact(&self) -> Result<usize> {
+        check_items(&self.prompt, &self.items, self.default)?;
+        if !is_interactive() {
+            return self
+                .default
+                .ok_or_else(|| Error::NotInteractive(self.prompt.clone()));
+        }
+        with_prompt(|term| {
+            let mut cursor = self.default.unwrap_or(0);
+            let chosen = {
+                let mut view = ListView::new(term)?;
+                loop {
+                    let rows = self
+                        .items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| {
+                            if i == cursor {
+                                format!("{} {}", style::ecyan("❯"), style::ecyan(item))
+                            } else {
+                                format!("  {item}")
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    view.draw(&self.prompt, "", &rows, cursor)?;
+                    match term.read_key_raw()? {
+                        Key::Enter => break Some(cursor),
+                        Key::Escape | Key::CtrlC => break None,
+                        key => {
+                            navigate(&key, &mut cursor, self.items.len());
+                        }
+                    }
+                }
+            };
+            match chosen {
+                Some(i) => {
+                    report(term, &self.prompt, &self.items[i])?;
+                    Ok(i)
+                }
+                None => Err(Error::PromptCancelled(self.prompt.clone())),
+            }
+        })
+    }
+}
+
+/// A choice of any number of items from a list.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct MultiSelect {
+    prompt: String,
+    items: Vec<String>,
+    defaults: Vec<usize>,
+}
+
+impl MultiSelect {
+    /// Creates a multiple-choice prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            items: vec![],
+            defaults: vec![],
+        }
+    }
+
+    /// Adds items to choose from.
+    pub fn items<I, S>(mut self, items: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.items.extend(items.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the indices of the initially checked items, which are also the
+    /// answer when there is no terminal to ask on.
+    pub fn defaults(mut self, indices: impl IntoIterator<Item = usize>) -> Self {
+        self.defaults = indices.into_iter().collect();
+        self
+    }
+
+    /// Shows the list and returns the indices of the checked items, in order.
+    ///
+    /// Arrow keys (or `j`/`k`) move the highlight, Space checks or unchecks
+    /// an item, `a` checks or unchecks all, and Enter confirms.
+    ///
+    /// # Errors
+    ///
+    /// Like [`Select::interact`], fails with [`Error::NoItems`] if there are
+    /// no items and with [`Error::InvalidDefault`] if a default is out of
+    /// range. Fails with [`Error::PromptCancelled`] on Escape or Ctrl-C.
+    /// Without a terminal this returns the defaults.
+    pub fn interact(&self) -> Result<Vec<usize>> {
+        check_items(
+            &self.prompt,
+            &self.items,
+            self.defaults.iter().max().copied(),
+        )?;
+        let mut checked = vec![false; self.items.len()];
+        for &i in &self.defaults {
+            checked[i] = true;
+        }
+        let selection = |checked: &[bool]| {
+            (0..checked.len())
+                .filter(|&i| checked[i])
+                .collect::<Vec<_>>()
+        };
+        if !is_interactive() {
+            return Ok(selection(&checked));
+        }
+        with_prompt(|term| {
+            let mut cursor = 0;
+            let confirmed = {
+                let mut view = ListView::new(term)?;
+                loop {
+                    let rows = self
+                        .items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| {
+                            let mark = if checked[i] {
+                                style::egreen("◉").to_string()
+                            } else {
+                                style::edim("◯").to_string()
+                            };
+                            if i == cursor {
+                                format!("{} {mark} {item}", style::ecyan("❯"))
+                            } else {
+                                format!("  {mark} {item}")
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    view.draw(&self.prompt, "(space to toggle)", &rows, cursor)?;
+                    match term.read_key_raw()? {
+                        Key::Char(' ') => checked[cursor] = !checked[cursor],
+                        Key::Char('a') => {
+                            let all = checked.iter().all(|&c| c);
+                            checked.iter_mut().for_each(|c| *c = !all);
+                        }
+                        Key::Enter => break true,
+                        Key::Escape | Key::CtrlC => break false,
+                        key => {
+                            navigate(&key, &mut cursor, self.items.len());
+                        }
+                    }
+                }
+            };
+            if !confirmed {
+                return Err(Error::PromptCancelled(self.prompt.clone()));
+            }
+            let selected = selection(&checked);
+            let answer = selected
+                .iter()
+                .map(|&i| self.items[i].as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            report(term, &self.prompt, &answer)?;
+            Ok(selected)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigate_wraps_around() {
+        let mut cursor = 0;
+        assert!(navigate(&Key::ArrowUp, &mut cursor, 3));
+        assert_eq!(cursor, 2);
+        assert!(navigate(&Key::Char('j'), &mut cursor, 3));
+        assert_eq!(cursor, 0);
+        assert!(navigate(&Key::End, &mut cursor, 3));
+        assert_eq!(cursor, 2);
+        assert!(!navigate(&Key::Char('x'), &mut cursor, 3));
+        assert_eq!(cursor, 2);
+    }
+}
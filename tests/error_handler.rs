@@ -0,0 +1,30 @@
+//! Integration tests for the process-wide error handler.
+//!
+//! These live in their own test binary because the handler cannot be unset.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clx::progress::{
+    ProgressJobBuilder, ProgressOutput, ProgressStatus, error_count, set_error_handler, set_output,
+};
+
+#[test]
+fn test_render_errors_go_to_the_error_handler() {
+    set_output(ProgressOutput::Text);
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    set_error_handler({
+        let handled = handled.clone();
+        move |_| {
+            handled.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    let errors_before = error_count();
+
+    let job = ProgressJobBuilder::new().body("{{ not_a_prop }}").start();
+    job.set_status(ProgressStatus::Done);
+
+    assert!(handled.load(Ordering::Relaxed) >= 1);
+    assert!(error_count() > errors_before);
+}
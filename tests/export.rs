@@ -0,0 +1,60 @@
+//! Verifies that timing exports include jobs recorded after they finished.
+
+use clx::progress::{
+    ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus, set_job_history,
+    write_chrome_trace, write_folded_stacks,
+};
+use serde_json::Value;
+
+/// Returns the statuses of the exported jobs named `name`.
+fn exported_statuses(name: &str) -> Vec<String> {
+    let mut trace = vec![];
+    write_chrome_trace(&mut trace).unwrap();
+    let trace: Value = serde_json::from_slice(&trace).unwrap();
+    trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|e| e["ph"] == "X" && e["name"] == name)
+        .map(|e| e["args"]["status"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_finished_jobs_are_exported_once() {
+    set_job_history(true);
+    let job = ProgressJobBuilder::new()
+        .prop("message", "recorded once")
+        .build();
+    job.set_status(ProgressStatus::Done);
+    job.set_status(ProgressStatus::Warn);
+    assert_eq!(exported_statuses("recorded once"), ["Warn"]);
+
+    job.set_status(ProgressStatus::Running);
+    assert!(exported_statuses("recorded once").is_empty());
+}
+
+#[test]
+fn test_print_jobs_are_exported_after_they_leave_the_tree() {
+    set_job_history(true);
+    let build = ProgressJobBuilder::new().prop("message", "build").start();
+    let printed = build.add(
+        ProgressJobBuilder::new()
+            .prop("message", "printed")
+            .on_done(ProgressJobDoneBehavior::Print)
+            .build(),
+    );
+    printed.set_status(ProgressStatus::Done);
+    assert!(build.children().is_empty());
+
+    let mut folded = vec![];
+    write_folded_stacks(&mut folded).unwrap();
+    build.remove();
+    let folded = String::from_utf8(folded).unwrap();
+    let paths = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .filter(|path| path.starts_with("build"))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, ["build", "build;printed"]);
+}
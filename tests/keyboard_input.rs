@@ -0,0 +1,121 @@
+//! Verifies that keys pressed while progress runs reach handlers and built-ins.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn keyboard_child_scenario() {
+    if std::env::var_os("CLX_KEYBOARD_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use std::sync::mpsc;
+
+    use clx::progress::{
+        Key, ProgressJobBuilder, ProgressStatus, on_key, set_interval, set_keyboard_input,
+    };
+
+    set_interval(Duration::from_millis(25));
+    set_keyboard_input(true);
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    on_key(move |key| {
+        let _ = tx.lock().unwrap().send(key);
+        key == Key::Char('x')
+    });
+    let job = ProgressJobBuilder::new().body("PARENT_ROW").start();
+    job.add(ProgressJobBuilder::new().body("CHILD_ROW").build());
+    println!("READY");
+
+    assert_eq!(rx.recv().unwrap(), Key::Char('x'));
+    assert_eq!(rx.recv().unwrap(), Key::Char('v'));
+    thread::sleep(Duration::from_millis(200));
+    println!("COLLAPSED");
+    assert_eq!(rx.recv().unwrap(), Key::Up);
+    job.set_status(ProgressStatus::Done);
+    clx::progress::stop();
+    let termios = nix::sys::termios::tcgetattr(std::io::stdin()).unwrap();
+    assert!(
+        termios
+            .local_flags
+            .contains(nix::sys::termios::LocalFlags::ECHO | nix::sys::termios::LocalFlags::ICANON)
+    );
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[test]
+fn keys_reach_handler_and_toggle_collapsed_children() {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "keyboard_child_scenario", "--nocapture"]);
+    cmd.env("CLX_KEYBOARD_PTY_SCENARIO", "1");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let mut writer = pair.master.take_writer().expect("take writer");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        let mut sent = false;
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if !sent && output.windows(9).any(|w| w == b"CHILD_ROW") {
+                sent = true;
+                thread::sleep(Duration::from_millis(100));
+                let _ = writer.write_all(b"xv\x1b[A");
+                let _ = writer.flush();
+            }
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    assert!(
+        !output.contains("xv"),
+        "keys were echoed: {}",
+        output.escape_debug()
+    );
+    // Collapsing erases the child row below the unchanged parent row.
+    let collapsed = output.find("COLLAPSED").unwrap();
+    let before = &output[..collapsed];
+    let child = before.rfind("CHILD_ROW").unwrap();
+    assert!(
+        before[child..].contains("\x1b[1A\r\x1b[0J"),
+        "children were not collapsed: {}",
+        output.escape_debug()
+    );
+    // The final frame shows the whole tree again.
+    let after = &output[collapsed..];
+    assert!(
+        after.contains("CHILD_ROW"),
+        "final frame left out children: {}",
+        output.escape_debug()
+    );
+}
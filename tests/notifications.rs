@@ -0,0 +1,95 @@
+//! Verifies that finished top-level jobs send a desktop notification.
+#![cfg(unix)]
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn notification_child_scenario() {
+    if std::env::var_os("CLX_NOTIFY_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use clx::osc::set_notify_after;
+    use clx::progress::{
+        ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus, set_interval, stop,
+    };
+
+    set_interval(Duration::from_millis(25));
+    set_notify_after(Some(Duration::ZERO));
+    let job = ProgressJobBuilder::new()
+        .prop("message", "NOTIFY_PARENT")
+        .on_done(ProgressJobDoneBehavior::Print)
+        .start();
+    let child = job.add(
+        ProgressJobBuilder::new()
+            .prop("message", "NOTIFY_CHILD")
+            .build(),
+    );
+    thread::sleep(Duration::from_millis(100));
+    child.set_status(ProgressStatus::Done);
+    // A `Print` job has left the tree by the time it notifies.
+    job.set_status(ProgressStatus::Done);
+    stop();
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[test]
+fn print_jobs_notify_when_they_finish() {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "notification_child_scenario", "--nocapture"]);
+    cmd.env("CLX_NOTIFY_PTY_SCENARIO", "1");
+    cmd.env("TERM_PROGRAM", "iTerm.app");
+    cmd.env_remove("TMUX");
+    cmd.env_remove("STY");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    assert!(
+        output.contains("\x1b]9;NOTIFY_PARENT: Done in"),
+        "top-level job did not notify: {}",
+        output.escape_debug()
+    );
+    assert!(
+        !output.contains("\x1b]9;NOTIFY_CHILD"),
+        "child job notified: {}",
+        output.escape_debug()
+    );
+}
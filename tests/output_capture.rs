@@ -0,0 +1,114 @@
+//! Verifies that captured stdout/stderr writes are printed above the frame.
+#![cfg(unix)]
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn capture_child_scenario() {
+    if std::env::var_os("CLX_CAPTURE_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use clx::progress::{
+        ProgressJobBuilder, ProgressStatus, capture_output, is_capturing_output, set_interval,
+    };
+
+    set_interval(Duration::from_millis(25));
+    capture_output().unwrap();
+    assert!(is_capturing_output());
+    let job = ProgressJobBuilder::new().body("FRAME_ROW").start();
+    thread::sleep(Duration::from_millis(200));
+    println!("CAPTURED_STDOUT");
+    eprintln!("CAPTURED_STDERR");
+    print!("PARTIAL_LINE");
+    // Lines from the tracing layer go to the terminal directly, not through fd 2
+    // and back in through the capture pipe.
+    #[cfg(feature = "tracing")]
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+        let subscriber = tracing_subscriber::registry().with(clx::progress::ProgressLayer::new());
+        tracing::subscriber::with_default(subscriber, || {
+            // Longer than the pipe buffer, and with a newline the capture
+            // reader would act on, so a write that went back through the pipe
+            // would block holding the terminal lock the reader waits for.
+            tracing::info!("TRACED_LINE\n{}", "x".repeat(256 * 1024));
+        });
+    }
+    thread::sleep(Duration::from_millis(200));
+    job.set_status(ProgressStatus::Done);
+    clx::progress::stop();
+    assert!(!is_capturing_output());
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[test]
+fn captured_output_is_printed_once_and_released_on_stop() {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "capture_child_scenario", "--nocapture"]);
+    cmd.env("CLX_CAPTURE_PTY_SCENARIO", "1");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    let mut markers = vec!["CAPTURED_STDOUT", "CAPTURED_STDERR", "PARTIAL_LINE"];
+    if cfg!(feature = "tracing") {
+        markers.push("TRACED_LINE");
+    }
+    for marker in markers {
+        assert_eq!(
+            output.matches(marker).count(),
+            1,
+            "{marker} not printed exactly once: {}",
+            output.escape_debug()
+        );
+    }
+    let partial = output.find("PARTIAL_LINE").unwrap();
+    let after = output.find("AFTER_STOP").unwrap();
+    assert!(
+        partial < after,
+        "partial line was not flushed before release: {}",
+        output.escape_debug()
+    );
+    assert!(
+        output.contains("FRAME_ROW"),
+        "frame was not drawn: {}",
+        output.escape_debug()
+    );
+}
@@ -0,0 +1,31 @@
+//! Verifies that the panic hook fails the job the panicking thread entered.
+//!
+//! Installing the hook changes how every later panic in the process is
+//! printed, so this runs in its own test binary.
+
+use std::sync::Arc;
+use std::thread;
+
+use clx::progress::{ProgressJob, ProgressJobBuilder, ProgressStatus, install_panic_hook};
+
+fn status(job: &ProgressJob) -> ProgressStatus {
+    job.status_history().pop().unwrap().0
+}
+
+#[test]
+fn test_panic_fails_entered_job() {
+    install_panic_hook();
+    let outer = Arc::new(ProgressJobBuilder::new().build());
+    let inner = Arc::new(ProgressJobBuilder::new().build());
+    let (outer_job, inner_job) = (outer.clone(), inner.clone());
+    let result = thread::spawn(move || {
+        let _outer = outer_job.enter();
+        let _inner = inner_job.enter();
+        panic!("test panic");
+    })
+    .join();
+
+    assert!(result.is_err());
+    assert_eq!(status(&inner), ProgressStatus::Failed);
+    assert_eq!(status(&outer), ProgressStatus::Running);
+}
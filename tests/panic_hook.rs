@@ -0,0 +1,101 @@
+//! Verifies that the panic hook prints panic messages clear of the frame.
+#![cfg(unix)]
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn panic_child_scenario() {
+    if std::env::var_os("CLX_PANIC_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use clx::progress::{ProgressJobBuilder, install_panic_hook, set_interval};
+
+    set_interval(Duration::from_millis(25));
+    install_panic_hook();
+    let job = ProgressJobBuilder::new().prop("message", "JOB_ROW").start();
+    let other = ProgressJobBuilder::new()
+        .prop("message", "OTHER_ROW")
+        .start();
+    thread::sleep(Duration::from_millis(200));
+    let worker = job.clone();
+    let result = thread::spawn(move || {
+        let _entered = worker.enter();
+        worker.log("JOB_LOG_LINE");
+        panic!("WORKER_PANIC");
+    })
+    .join();
+    assert!(result.is_err());
+    thread::sleep(Duration::from_millis(200));
+    other.set_status(clx::progress::ProgressStatus::Done);
+    clx::progress::stop();
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[test]
+fn panic_message_is_printed_above_frame() {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "panic_child_scenario", "--nocapture"]);
+    cmd.env("CLX_PANIC_PTY_SCENARIO", "1");
+    cmd.env("RUST_BACKTRACE", "0");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    let panic = output.find("WORKER_PANIC").expect("panic message missing");
+    // The frame was cleared right before the message was printed.
+    assert!(
+        output[..panic].rfind("\x1b[0J") > output[..panic].rfind("OTHER_ROW"),
+        "frame was not cleared before the message: {}",
+        output.escape_debug()
+    );
+    let after = &output[panic..];
+    assert!(
+        after.contains("✗") && after.contains("JOB_ROW"),
+        "entered job was not redrawn as failed: {}",
+        output.escape_debug()
+    );
+    // Failing the job prints its log, as `set_status(Failed)` does.
+    assert!(
+        output.contains("JOB_LOG_LINE"),
+        "failed job's log was not printed: {}",
+        output.escape_debug()
+    );
+}
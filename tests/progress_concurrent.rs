@@ -73,6 +73,62 @@ fn test_concurrent_progress_updates() {
     job_arc.set_status(ProgressStatus::Done);
 }
 
+#[test]
+fn test_concurrent_increments_are_not_lost() {
+    setup();
+
+    let job = ProgressJobBuilder::new()
+        .prop("message", "Concurrent increments")
+        .progress_total(100_000)
+        .start();
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let job = Arc::clone(&job);
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    job.increment(1);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(job.overall_progress(), Some((8000, 100_000)));
+    job.set_status(ProgressStatus::Done);
+}
+
+#[test]
+fn test_concurrent_increments_clamp_to_total() {
+    setup();
+
+    let job = ProgressJobBuilder::new()
+        .prop("message", "Clamped increments")
+        .progress_total(500)
+        .start();
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let job = Arc::clone(&job);
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    job.increment(1);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(job.overall_progress(), Some((500, 500)));
+    job.set_status(ProgressStatus::Done);
+}
+
 #[test]
 fn test_concurrent_child_additions() {
     setup();
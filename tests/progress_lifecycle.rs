@@ -194,6 +194,123 @@ fn test_done_behavior_collapse() {
     assert_eq!(job.children().len(), 1);
 }
 
+#[test]
+fn test_done_behavior_print() {
+    setup();
+
+    let parent = ProgressJobBuilder::new().prop("message", "Parent").start();
+    let child = parent.add(
+        ProgressJobBuilder::new()
+            .prop("message", "Printed child")
+            .on_done(ProgressJobDoneBehavior::Print)
+            .build(),
+    );
+    assert_eq!(parent.children().len(), 1);
+
+    // Finishing the job moves it out of the live frame
+    child.set_status(ProgressStatus::Done);
+    assert!(!child.is_running());
+    assert!(parent.children().is_empty());
+
+    parent.set_status(ProgressStatus::Done);
+}
+
+#[cfg(unix)]
+#[test]
+fn done_behavior_print_child_scenario() {
+    if std::env::var_os("CLX_PRINT_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use std::thread;
+    use std::time::Duration;
+
+    clx::progress::set_interval(Duration::from_millis(25));
+    let parent = ProgressJobBuilder::new()
+        .prop("message", "PARENT_ROW")
+        .start();
+    let child = parent.add(
+        ProgressJobBuilder::new()
+            .prop("message", "PRINTED_ROW")
+            .on_done(ProgressJobDoneBehavior::Print)
+            .build(),
+    );
+    thread::sleep(Duration::from_millis(200));
+    child.set_status(ProgressStatus::Done);
+    thread::sleep(Duration::from_millis(200));
+    clx::progress::stop();
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[cfg(unix)]
+#[test]
+fn done_behavior_print_writes_the_line_once_above_the_frame() {
+    use std::io::Read;
+    use std::thread;
+
+    use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args([
+        "--exact",
+        "done_behavior_print_child_scenario",
+        "--nocapture",
+    ]);
+    cmd.env("CLX_PRINT_PTY_SCENARIO", "1");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    let plain = console::strip_ansi_codes(&output);
+    assert_eq!(
+        plain.matches("✔ PRINTED_ROW").count(),
+        1,
+        "finished line was not printed exactly once: {}",
+        output.escape_debug()
+    );
+    // Later frames redraw the parent below the printed line, without the child.
+    let printed = plain.find("✔ PRINTED_ROW").unwrap();
+    let after = &plain[printed + "✔ PRINTED_ROW".len()..];
+    assert!(
+        after.contains("PARENT_ROW") && !after.contains("PRINTED_ROW"),
+        "frame was not redrawn below the printed line: {}",
+        output.escape_debug()
+    );
+}
+
 #[test]
 fn test_deeply_nested_jobs() {
     setup();
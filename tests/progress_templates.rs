@@ -0,0 +1,182 @@
+//! Integration tests for template validation and render error reporting.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use clx::Error;
+use clx::progress::{
+    Body, ProgressJob, ProgressJobBuilder, ProgressOutput, ProgressStatus, TemplateEngine,
+    set_output,
+};
+
+/// Set up text mode for testing (no terminal interaction needed)
+fn setup() {
+    set_output(ProgressOutput::Text);
+}
+
+#[test]
+fn test_try_build_accepts_valid_templates() {
+    setup();
+
+    let job = ProgressJobBuilder::new()
+        .body("{{ spinner() }} {{ message | cyan }} {{ progress_bar(flex=true) }}")
+        .body_text(Some("{{ message }} {{ percentage() }}"))
+        .try_build();
+    assert!(job.is_ok());
+}
+
+#[test]
+fn test_try_build_rejects_unknown_functions_and_filters() {
+    setup();
+
+    for engine in [TemplateEngine::default(), TemplateEngine::Simple] {
+        for body in [
+            "{{ spinnr() }}",
+            "{{ message | cyna }}",
+            "{{ message ",
+            "{{ spinner(name='typo') }}",
+        ] {
+            let result = ProgressJobBuilder::new()
+                .engine(engine)
+                .body(body)
+                .try_build();
+            assert!(result.is_err(), "{engine:?} accepted {body:?}");
+        }
+    }
+}
+
+#[test]
+fn test_try_build_checks_body_text() {
+    setup();
+
+    let result = ProgressJobBuilder::new()
+        .body_text(Some("{{ nope() }}"))
+        .try_build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_try_start_returns_error_without_starting() {
+    setup();
+
+    let result = ProgressJobBuilder::new()
+        .body("{{ message | nope }}")
+        .try_start();
+    assert!(result.is_err());
+
+    let job = ProgressJobBuilder::new()
+        .prop("message", "valid")
+        .try_start()
+        .unwrap();
+    job.set_status(ProgressStatus::Done);
+}
+
+#[test]
+fn test_on_render_error_reports_each_error_once() {
+    setup();
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let job = ProgressJobBuilder::new()
+        .body("{{ missing }}")
+        .on_render_error({
+            let errors = errors.clone();
+            move |_| {
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .start();
+
+    job.prop("message", "still broken");
+    job.prop("message", "and again");
+    assert_eq!(errors.load(Ordering::Relaxed), 1);
+
+    job.set_status(ProgressStatus::Done);
+}
+
+#[cfg(feature = "tera")]
+#[test]
+fn test_unknown_spinner_from_a_prop_fails_the_render() {
+    setup();
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let job = ProgressJobBuilder::new()
+        .body("{{ spinner(name=style) }}")
+        .prop("style", "typo")
+        .on_render_error({
+            let errors = errors.clone();
+            move |err| {
+                assert!(err.to_string().contains("unknown spinner"), "{err}");
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .start();
+    assert_eq!(errors.load(Ordering::Relaxed), 1);
+
+    job.set_status(ProgressStatus::Done);
+}
+
+#[test]
+fn test_on_render_error_can_update_the_job() {
+    setup();
+
+    let failing: Arc<OnceLock<Arc<ProgressJob>>> = Arc::new(OnceLock::new());
+    let job = ProgressJobBuilder::new()
+        .body("{{ missing }}")
+        .on_render_error({
+            let failing = failing.clone();
+            move |_| {
+                // Would deadlock if called with the template cache held.
+                if let Some(job) = failing.get() {
+                    job.set_status(ProgressStatus::Failed);
+                }
+            }
+        })
+        .build();
+    let job = Arc::new(job);
+    failing.set(job.clone()).unwrap();
+
+    job.update();
+    let history = job.status_history();
+    assert_eq!(history.last().unwrap().0, ProgressStatus::Failed);
+}
+
+#[test]
+fn test_body_builder_is_accepted_by_body() {
+    setup();
+
+    let job = ProgressJobBuilder::new()
+        .body(Body::new().spinner().prop("message"))
+        .prop("message", "typed")
+        .try_build();
+    assert!(job.is_ok());
+
+    let result = ProgressJobBuilder::new()
+        .body(Body::new().prop("not valid"))
+        .try_build();
+    assert!(matches!(result, Err(Error::Template(msg)) if msg.contains("invalid property name")));
+
+    let result = ProgressJobBuilder::new()
+        .body(Body::new().spinner_named("typo"))
+        .try_start();
+    assert!(matches!(result, Err(Error::Template(msg)) if msg.contains("unknown spinner")));
+}
+
+#[test]
+fn test_invalid_body_builder_fails_when_rendered() {
+    setup();
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let job = ProgressJobBuilder::new()
+        .body(Body::new().prop("not valid"))
+        .on_render_error({
+            let errors = errors.clone();
+            move |err| {
+                assert!(matches!(err, Error::Template(_)));
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .start();
+    assert_eq!(errors.load(Ordering::Relaxed), 1);
+
+    job.set_status(ProgressStatus::Done);
+}
@@ -0,0 +1,94 @@
+//! Integration tests for the `tracing` layer.
+
+#![cfg(feature = "tracing")]
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use clx::progress::{
+    ProgressJobDoneBehavior, ProgressLayer, ProgressOutput, active_jobs, job_count, set_output,
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Set up text mode for testing (no terminal interaction needed). The tests
+/// count jobs, which closed spans remove, so they run one at a time.
+fn setup() -> MutexGuard<'static, ()> {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    let guard = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+    set_output(ProgressOutput::Text);
+    guard
+}
+
+#[test]
+fn test_spans_become_nested_jobs() {
+    let _serial = setup();
+
+    let subscriber = tracing_subscriber::registry().with(ProgressLayer::new().with_span_jobs(true));
+    tracing::subscriber::with_default(subscriber, || {
+        let jobs_before = job_count();
+        let active_before = active_jobs();
+
+        let outer = tracing::info_span!("outer", count = 1);
+        let outer_guard = outer.enter();
+        assert_eq!(job_count(), jobs_before + 1);
+
+        {
+            let inner = tracing::info_span!("inner", message = "working");
+            let _inner_guard = inner.enter();
+            inner.record("message", "still working");
+            tracing::info!(step = 1, "inside the span");
+            // The inner job is a child of the outer job, not a new top-level job
+            assert_eq!(job_count(), jobs_before + 1);
+            assert_eq!(active_jobs(), active_before + 2);
+        }
+        assert_eq!(active_jobs(), active_before + 1);
+
+        drop(outer_guard);
+        drop(outer);
+        assert_eq!(active_jobs(), active_before);
+    });
+}
+
+#[test]
+fn test_spans_are_not_jobs_by_default() {
+    let _serial = setup();
+
+    let subscriber = tracing_subscriber::registry().with(ProgressLayer::new());
+    tracing::subscriber::with_default(subscriber, || {
+        let jobs_before = job_count();
+        let span = tracing::info_span!("quiet");
+        let _guard = span.enter();
+        tracing::warn!("no job for this span");
+        assert_eq!(job_count(), jobs_before);
+    });
+}
+
+#[test]
+fn test_closed_spans_leave_the_frame() {
+    let _serial = setup();
+
+    let subscriber = tracing_subscriber::registry().with(ProgressLayer::new().with_span_jobs(true));
+    tracing::subscriber::with_default(subscriber, || {
+        let jobs_before = job_count();
+        for i in 0..10 {
+            let span = tracing::info_span!("step", i);
+            let _guard = span.enter();
+            assert_eq!(job_count(), jobs_before + 1);
+        }
+        assert_eq!(job_count(), jobs_before);
+    });
+}
+
+#[test]
+fn test_closed_spans_can_be_kept() {
+    let _serial = setup();
+
+    let layer = ProgressLayer::new()
+        .with_span_jobs(true)
+        .with_span_done(ProgressJobDoneBehavior::Keep);
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let jobs_before = job_count();
+        drop(tracing::info_span!("kept").entered());
+        assert_eq!(job_count(), jobs_before + 1);
+    });
+}
@@ -0,0 +1,207 @@
+//! Integration tests for interactive prompts.
+
+use clx::Error;
+use clx::progress::{ProgressOutput, set_output};
+use clx::prompt::{Confirm, MultiSelect, Select, Text};
+
+#[test]
+fn test_prompts_use_defaults_without_a_terminal() {
+    set_output(ProgressOutput::Text);
+
+    assert!(Confirm::new("Continue?").default(true).interact().unwrap());
+    assert_eq!(Text::new("Name").default("clx").interact().unwrap(), "clx");
+    assert_eq!(
+        Select::new("Pick")
+            .items(["a", "b", "c"])
+            .default(2)
+            .interact()
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        MultiSelect::new("Pick")
+            .items(["a", "b", "c"])
+            .defaults([2, 0])
+            .interact()
+            .unwrap(),
+        [0, 2]
+    );
+}
+
+#[test]
+fn test_prompts_without_defaults_fail_without_a_terminal() {
+    set_output(ProgressOutput::Text);
+
+    assert!(matches!(
+        Confirm::new("Continue?").interact(),
+        Err(Error::NotInteractive(prompt)) if prompt == "Continue?"
+    ));
+    assert!(matches!(
+        Text::new("Name").interact(),
+        Err(Error::NotInteractive(_))
+    ));
+    assert!(matches!(
+        Select::new("Pick").items(["a"]).interact(),
+        Err(Error::NotInteractive(_))
+    ));
+    assert!(
+        MultiSelect::new("Pick")
+            .items(["a"])
+            .interact()
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_select_without_items_fails() {
+    set_output(ProgressOutput::Text);
+
+    assert!(matches!(
+        Select::new("Pick").default(0).interact(),
+        Err(Error::NoItems(prompt)) if prompt == "Pick"
+    ));
+    assert!(matches!(
+        MultiSelect::new("Pick").interact(),
+        Err(Error::NoItems(prompt)) if prompt == "Pick"
+    ));
+}
+
+#[test]
+fn test_select_with_out_of_range_default_fails() {
+    set_output(ProgressOutput::Text);
+
+    assert!(matches!(
+        Select::new("Pick").items(["a", "b"]).default(2).interact(),
+        Err(Error::InvalidDefault(prompt)) if prompt == "Pick"
+    ));
+    assert!(matches!(
+        MultiSelect::new("Pick")
+            .items(["a", "b"])
+            .defaults([0, 5])
+            .interact(),
+        Err(Error::InvalidDefault(prompt)) if prompt == "Pick"
+    ));
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+    #[test]
+    fn prompt_child_scenario() {
+        if std::env::var_os("CLX_PROMPT_PTY_SCENARIO").is_none() {
+            return;
+        }
+
+        use clx::progress::{ProgressJobBuilder, ProgressStatus, set_interval};
+        use clx::prompt::{Confirm, MultiSelect, Select, Text};
+
+        set_interval(Duration::from_millis(25));
+        let job = ProgressJobBuilder::new().body("FRAME_ROW").start();
+        thread::sleep(Duration::from_millis(100));
+        println!("READY");
+        let confirm = Confirm::new("Continue?").interact().unwrap();
+        let text = Text::new("Name").interact().unwrap();
+        let select = Select::new("Pick one")
+            .items(["alpha", "beta", "gamma"])
+            .interact()
+            .unwrap();
+        let multi = MultiSelect::new("Pick many")
+            .items(["alpha", "beta", "gamma"])
+            .interact()
+            .unwrap();
+        job.set_status(ProgressStatus::Done);
+        clx::progress::stop();
+        println!("RESULT {confirm} {text} {select} {multi:?}");
+
+        std::process::exit(0);
+    }
+
+    #[test]
+    fn prompts_read_keys_and_restore_the_display() {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .expect("openpty");
+
+        let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+        cmd.args(["--exact", "pty::prompt_child_scenario", "--nocapture"]);
+        cmd.env("CLX_PROMPT_PTY_SCENARIO", "1");
+
+        let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader().expect("clone reader");
+        let mut writer = pair.master.take_writer().expect("take writer");
+        let (tx, rx) = mpsc::channel();
+        let reader_thread = thread::spawn(move || {
+            let mut chunk = [0; 4096];
+            while let Ok(count) = reader.read(&mut chunk) {
+                if count == 0 || tx.send(chunk[..count].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut output = String::new();
+        let mut wait_for = |marker: &str| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !output.contains(marker) {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(timeout) {
+                    Ok(chunk) => output.push_str(&String::from_utf8_lossy(&chunk)),
+                    Err(_) => panic!("timed out waiting for {marker}: {}", output.escape_debug()),
+                }
+            }
+        };
+
+        wait_for("READY");
+        wait_for("Continue?");
+        writer.write_all(b"y").unwrap();
+        wait_for("Name");
+        writer.write_all(b"clxx\x7f\r").unwrap();
+        wait_for("Pick one");
+        writer.write_all(b"\x1b[B").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        writer.write_all(b"\r").unwrap();
+        wait_for("Pick many");
+        writer.write_all(b" jj ").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        writer.write_all(b"\r").unwrap();
+        wait_for("RESULT");
+        wait_for("\n");
+
+        child.wait().expect("wait child");
+        drop(writer);
+        drop(pair.master);
+        let _ = reader_thread.join();
+
+        assert!(
+            output.contains("RESULT true clx 1 [0, 2]"),
+            "unexpected answers: {}",
+            output.escape_debug()
+        );
+        assert!(
+            output.contains("Pick one") && output.contains("beta"),
+            "select summary missing: {}",
+            output.escape_debug()
+        );
+        let summary = output.rfind("Continue?").unwrap();
+        let frame = output.rfind("FRAME_ROW").unwrap();
+        assert!(
+            frame > summary,
+            "display was not redrawn after the prompts: {}",
+            output.escape_debug()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Verifies that SIGINT finalizes the progress display before the process exits.
+#![cfg(unix)]
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn signal_child_scenario() {
+    let Some(mode) = std::env::var_os("CLX_SIGNAL_PTY_SCENARIO") else {
+        return;
+    };
+
+    use clx::progress::{ProgressJobBuilder, handle_signals, on_signal, set_interval};
+    use nix::sys::signal::{Signal, raise};
+
+    set_interval(Duration::from_millis(25));
+    handle_signals().unwrap();
+    if mode == "callback" {
+        on_signal(|signal| {
+            println!("CALLBACK {signal} {}", signal.number());
+            std::process::exit(3);
+        });
+    }
+    let job = ProgressJobBuilder::new().prop("message", "JOB_ROW").start();
+    job.add(
+        ProgressJobBuilder::new()
+            .prop("message", "CHILD_ROW")
+            .build(),
+    );
+    thread::sleep(Duration::from_millis(200));
+    raise(Signal::SIGINT).unwrap();
+    thread::sleep(Duration::from_secs(5));
+    println!("NOT_INTERRUPTED");
+
+    std::process::exit(0);
+}
+
+fn run_scenario(mode: &str) -> (portable_pty::ExitStatus, String) {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "signal_child_scenario", "--nocapture"]);
+    cmd.env("CLX_SIGNAL_PTY_SCENARIO", mode);
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = reader.read_to_end(&mut output);
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    drop(pair.master);
+    let output = reader_thread.join().expect("join reader");
+    (status, String::from_utf8_lossy(&output).into_owned())
+}
+
+#[test]
+fn sigint_finalizes_frame_and_reraises() {
+    let (status, output) = run_scenario("reraise");
+    assert!(
+        !status.success(),
+        "child survived: {}",
+        output.escape_debug()
+    );
+    assert!(
+        !output.contains("NOT_INTERRUPTED"),
+        "signal was not raised again: {}",
+        output.escape_debug()
+    );
+    let last_frame = output.rfind("JOB_ROW").expect("frame was not drawn");
+    let tail = &output[last_frame.saturating_sub(32)..];
+    assert_eq!(
+        tail.matches('⊘').count(),
+        2,
+        "running jobs were not cancelled: {}",
+        output.escape_debug()
+    );
+    assert!(
+        tail.contains("\x1b[?25h"),
+        "cursor was not shown again: {}",
+        output.escape_debug()
+    );
+}
+
+#[test]
+fn sigint_invokes_callback() {
+    let (status, output) = run_scenario("callback");
+    assert_eq!(status.exit_code(), 3, "output: {}", output.escape_debug());
+    assert!(
+        output.contains("CALLBACK SIGINT 2"),
+        "callback did not run: {}",
+        output.escape_debug()
+    );
+}
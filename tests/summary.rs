@@ -0,0 +1,96 @@
+//! Verifies that the summary counts jobs that left the tree before `stop`.
+#![cfg(unix)]
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+
+#[test]
+fn summary_child_scenario() {
+    if std::env::var_os("CLX_SUMMARY_PTY_SCENARIO").is_none() {
+        return;
+    }
+
+    use clx::progress::{
+        ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus, Summary, set_interval,
+        set_summary, stop,
+    };
+
+    set_interval(Duration::from_millis(25));
+    set_summary(Some(Summary::new().body("SUMMARY {{ counts }}")));
+    let printed = ProgressJobBuilder::new()
+        .prop("message", "PRINTED_ROW")
+        .on_done(ProgressJobDoneBehavior::Print)
+        .start();
+    let removed = ProgressJobBuilder::new()
+        .prop("message", "REMOVED_ROW")
+        .start();
+    thread::sleep(Duration::from_millis(100));
+    printed.set_status(ProgressStatus::Done);
+    removed.set_status(ProgressStatus::Done);
+    // A later final status replaces the recorded one.
+    removed.set_status(ProgressStatus::Warn);
+    removed.remove();
+    stop();
+    // The record was dropped once the summary was printed.
+    stop();
+    println!("AFTER_STOP");
+
+    std::process::exit(0);
+}
+
+#[test]
+fn summary_counts_jobs_that_left_the_tree() {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .expect("openpty");
+
+    let mut cmd = CommandBuilder::new(std::env::current_exe().expect("current_exe"));
+    cmd.args(["--exact", "summary_child_scenario", "--nocapture"]);
+    cmd.env("CLX_SUMMARY_PTY_SCENARIO", "1");
+
+    let mut child = pair.slave.spawn_command(cmd).expect("spawn child");
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().expect("clone reader");
+    let reader_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut chunk = [0; 4096];
+        while let Ok(count) = reader.read(&mut chunk) {
+            if count == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..count]);
+            if output.windows(10).any(|w| w == b"AFTER_STOP") {
+                break;
+            }
+        }
+        output
+    });
+
+    let status = child.wait().expect("wait child");
+    let output = reader_thread.join().expect("join reader");
+    drop(pair.master);
+    let output = String::from_utf8_lossy(&output);
+    assert!(status.success(), "child failed: {}", output.escape_debug());
+
+    let plain = console::strip_ansi_codes(&output);
+    assert!(
+        plain.contains("SUMMARY 2 jobs: 1 done, 1 warned"),
+        "summary did not count both jobs: {}",
+        output.escape_debug()
+    );
+    assert_eq!(
+        plain.matches("SUMMARY").count(),
+        1,
+        "jobs were still recorded after the summary was printed: {}",
+        output.escape_debug()
+    );
+}